@@ -0,0 +1,347 @@
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::{group, command};
+use serenity::model::application::command::Command;
+use serenity::model::channel::Message;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::*;
+
+use crate::commands::slash;
+use crate::config::bot_config::{ActivityConfig, ActivityKind, Config, ConfigContainer, StatusKind};
+use crate::database::connection::DatabaseContainer;
+use crate::utils::audit_log::AuditLogContainer;
+use crate::utils::embeds::action_embed;
+use crate::utils::errors::error_report_embed;
+use crate::utils::shutdown::{self, ShardManagerContainer};
+use crate::utils::utilities::rand_color;
+
+
+#[group]
+#[owners_only]
+#[commands(error_command, reload, activity, status, guilds, leaveguild, shards, sync, shutdown)]
+struct Admin;
+
+const GUILDS_PER_PAGE: usize = 20;
+
+#[command("error")]
+#[description = "Shows the full details of an error from the ID its user was given"]
+#[usage = "!error <id>"]
+#[min_args(1)]
+async fn error_command(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let id = args.single::<String>()?;
+    let id = id.trim_matches('`');
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    let report = match database.error_report(id)? {
+        Some(report) => report,
+        None => {
+            msg.channel_id.say(ctx, format!("There is no error with the ID `{}`", id)).await?;
+            return Ok(());
+        },
+    };
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| error_report_embed(e, &report))
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Reloads the config file. Changes to the token, intents, owners, database and metrics address only apply after a restart."]
+#[usage = "!reload"]
+async fn reload(ctx: &Context, msg: &Message) -> CommandResult {
+    let (container, audit_log) = {
+        let data = ctx.data.read().await;
+        (data.get::<ConfigContainer>().unwrap().clone(), data.get::<AuditLogContainer>().unwrap().clone())
+    };
+
+    let (path, profile) = {
+        let config = container.read().await;
+        (config.path.clone(), config.profile.clone())
+    };
+
+    let config = match Config::load(&path, &profile) {
+        Ok(config) => config,
+        Err(why) => {
+            msg.channel_id.say(ctx, format!("Could not reload, keeping the current config: {}", why)).await?;
+            return Ok(());
+        },
+    };
+
+    audit_log.reconfigure(config.audit_log.clone());
+    ctx.set_presence(Some(config.activity.to_activity()), config.status.to_online_status()).await;
+
+    let description = format!("Reloaded {} with profile `{}`", config.path.display(), config.profile);
+    *container.write().await = config;
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| action_embed(e, "Config Reloaded", description, &msg.author))
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Changes what the bot is shown doing, until the config is reloaded"]
+#[usage = "!activity <playing/listening/watching/competing> <text>"]
+#[min_args(2)]
+async fn activity(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let kind = match args.single::<String>()?.parse::<ActivityKind>() {
+        Ok(kind) => kind,
+        Err(()) => {
+            msg.channel_id.say(ctx, "The activity must be one of `playing`, `listening`, `watching` or `competing`").await?;
+            return Ok(());
+        },
+    };
+
+    let activity = ActivityConfig { kind, text: args.rest().trim().to_string() };
+
+    let status = {
+        let container = ctx.data.read().await.get::<ConfigContainer>().unwrap().clone();
+        let mut config = container.write().await;
+        config.activity = activity.clone();
+        config.status
+    };
+
+    ctx.set_presence(Some(activity.to_activity()), status.to_online_status()).await;
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| action_embed(e, "Activity Changed", format!("Now {} **{}**", format!("{:?}", activity.kind).to_lowercase(), activity.text), &msg.author))
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Changes the bot's online status, until the config is reloaded"]
+#[usage = "!status <online/idle/dnd/invisible>"]
+#[min_args(1)]
+async fn status(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let status = match args.single::<String>()?.parse::<StatusKind>() {
+        Ok(status) => status,
+        Err(()) => {
+            msg.channel_id.say(ctx, "The status must be one of `online`, `idle`, `dnd` or `invisible`").await?;
+            return Ok(());
+        },
+    };
+
+    let activity = {
+        let container = ctx.data.read().await.get::<ConfigContainer>().unwrap().clone();
+        let mut config = container.write().await;
+        config.status = status;
+        config.activity.to_activity()
+    };
+
+    ctx.set_presence(Some(activity), status.to_online_status()).await;
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| action_embed(e, "Status Changed", format!("Status set to **{:?}**", status), &msg.author))
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Lists the servers the bot is in"]
+#[usage = "!guilds [page]"]
+async fn guilds(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let page = args.single::<usize>().unwrap_or(1).max(1);
+
+    let mut guilds: Vec<(String, GuildId, u64)> = ctx.cache.guilds()
+        .into_iter()
+        .filter_map(|guild_id| ctx.cache.guild_field(guild_id, |guild| (guild.name.clone(), guild.id, guild.member_count)))
+        .collect();
+
+    guilds.sort_by_key(|(name, _, _)| name.to_lowercase());
+
+    let pages = guilds.len().div_ceil(GUILDS_PER_PAGE).max(1);
+    let page = page.min(pages);
+
+    let list = guilds.iter()
+        .skip((page - 1) * GUILDS_PER_PAGE)
+        .take(GUILDS_PER_PAGE)
+        .map(|(name, id, members)| format!("**{}** (`{}`) • {} members", name, id.0, members))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.title(format!("**Servers ({})**", guilds.len()))
+                .description(if list.is_empty() { "Not in any servers".to_string() } else { list })
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Page {}/{} • Requested by {}", page, pages, msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Makes the bot leave a server"]
+#[usage = "!leaveguild <server id>"]
+#[min_args(1)]
+async fn leaveguild(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match args.single::<u64>() {
+        Ok(id) => GuildId(id),
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please give the ID of a server, see `!guilds`").await?;
+            return Ok(());
+        },
+    };
+
+    let name = match ctx.cache.guild_field(guild_id, |guild| guild.name.clone()) {
+        Some(name) => name,
+        None => {
+            msg.channel_id.say(ctx, format!("I'm not in a server with the ID `{}`", guild_id.0)).await?;
+            return Ok(());
+        },
+    };
+
+    let manager = songbird::get(ctx).await.unwrap().clone();
+
+    if manager.get(guild_id).is_some() {
+        manager.remove(guild_id).await?;
+    }
+
+    guild_id.leave(ctx).await?;
+
+    // The invoking server may be the one we just left
+    if msg.guild_id != Some(guild_id) {
+        msg.channel_id.send_message(ctx, |m|
+            m.embed(|e| action_embed(e, "Left Server", format!("Left **{}** (`{}`)", name, guild_id.0), &msg.author))
+        ).await?;
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description = "Shows the connection stage, heartbeat latency and guild count of each shard this process runs"]
+#[usage = "!shards"]
+async fn shards(ctx: &Context, msg: &Message) -> CommandResult {
+    let shard_manager = ctx.data.read().await.get::<ShardManagerContainer>().unwrap().clone();
+
+    let mut runners: Vec<_> = {
+        let manager = shard_manager.lock().await;
+        let runners = manager.runners.lock().await;
+        runners.iter().map(|(id, runner)| (id.0, runner.stage, runner.latency)).collect()
+    };
+
+    runners.sort_by_key(|(id, _, _)| *id);
+
+    let total = ctx.cache.shard_count().max(1);
+    let mut guild_counts = vec![0; total as usize];
+
+    for guild_id in ctx.cache.guilds() {
+        guild_counts[serenity::utils::shard_id(guild_id, total) as usize] += 1;
+    }
+
+    let list = runners.iter()
+        .map(|(id, stage, latency)| format!(
+            "**Shard {}**{} • {:?} • {} • {} guild(s)",
+            id,
+            if *id == ctx.shard_id { " (this one)" } else { "" },
+            stage,
+            latency.map_or("no heartbeat yet".to_string(), |latency| format!("{} ms", latency.as_millis())),
+            guild_counts.get(*id as usize).copied().unwrap_or(0),
+        ))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.title(format!("**Shards ({} of {} in this process)**", runners.len(), total))
+                .description(list)
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Requested by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Registers the slash commands globally or in one server, or removes them with `clear`. Global changes can take up to an hour to show up."]
+#[usage = "!sync [clear] <global/guild> [server id]"]
+#[min_args(1)]
+async fn sync(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut scope = args.single::<String>()?.to_lowercase();
+    let clear = scope == "clear";
+
+    if clear {
+        scope = match args.single::<String>() {
+            Ok(scope) => scope.to_lowercase(),
+            Err(_) => {
+                msg.channel_id.say(ctx, "Say which commands to clear: `global` or `guild`").await?;
+                return Ok(());
+            },
+        };
+    }
+
+    let (registered, place) = match scope.as_str() {
+        "global" => {
+            let registered = if clear {
+                Command::set_global_application_commands(ctx, |c| c).await?
+            } else {
+                Command::set_global_application_commands(ctx, slash::create_commands).await?
+            };
+
+            (registered, "globally".to_string())
+        },
+        "guild" => {
+            let guild_id = match args.single::<u64>().map(GuildId).ok().or(msg.guild_id) {
+                Some(guild_id) => guild_id,
+                None => {
+                    msg.channel_id.say(ctx, "Give a server ID, or run this in the server").await?;
+                    return Ok(());
+                },
+            };
+
+            let registered = if clear {
+                guild_id.set_application_commands(ctx, |c| c).await?
+            } else {
+                guild_id.set_application_commands(ctx, slash::create_commands).await?
+            };
+
+            (registered, format!("in server `{}`", guild_id.0))
+        },
+        other => {
+            msg.channel_id.say(ctx, format!("`{}` isn't a scope, use `global` or `guild`", other)).await?;
+            return Ok(());
+        },
+    };
+
+    let description = if clear {
+        format!("Removed the slash commands {}", place)
+    } else {
+        format!("Registered {} slash command(s) {}", registered.len(), place)
+    };
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| action_embed(e, "Synced Slash Commands", description, &msg.author))
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Saves every queue, leaves all voice channels and shuts the bot down"]
+#[usage = "!shutdown"]
+async fn shutdown(ctx: &Context, msg: &Message) -> CommandResult {
+    let manager = songbird::get(ctx).await.unwrap().clone();
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| action_embed(e, "Shutting Down", "Leaving voice channels and disconnecting", &msg.author))
+    ).await?;
+
+    shutdown::shutdown(&ctx.data, &ctx.cache, &ctx.http, &manager).await;
+
+    Ok(())
+}
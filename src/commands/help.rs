@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 
+use serenity::builder::CreateEmbed;
 use serenity::framework::standard::macros::{help};
 use serenity::framework::standard::{
     help_commands,
@@ -13,6 +14,10 @@ use serenity::model::prelude::*;
 use serenity::prelude::*;
 use serenity::utils::Colour;
 
+use crate::commands::GROUPS;
+use crate::config::bot_config::ConfigContainer;
+use crate::database::guild_settings;
+use crate::utils::response::Response;
 use crate::utils::utilities::rand_color;
 
 
@@ -29,6 +34,7 @@ async fn help(
     owners: HashSet<UserId>,        
 ) -> CommandResult {
     let help_data = help_commands::create_customised_help_data(ctx, msg, &args, groups, &owners, help_options).await;
+    let prefix = guild_settings::prefix(ctx, msg.guild_id).await;
 
     let _ = msg.channel_id.send_message(ctx, |m| {
         m.embed(|e| {
@@ -44,31 +50,16 @@ async fn help(
 
                 // When running !help with no arguments (displaying all commands)
                 CustomisedHelpData::GroupedCommands { help_description, groups } => {
-                    e.title("**Bot Commands**")
-                    .description(help_description)
-                    .color(rand_color())
-                    .footer(|f| 
-                        f.text(format!("Requested by {}", msg.author.name))
-                        .icon_url(msg.author.face())
-                        );
-
-                    for i in 0..groups.len() {
-                        e.field(groups[i].name, groups[i].command_names.join("\n"), true);
-                    }
-
-                    e
+                    let groups: Vec<_> = groups.iter().map(|group| (group.name, group.command_names.clone())).collect();
+                    grouped_embed(e, &help_description, &groups, &prefix, &msg.author)
                 },
 
                 // When getting help for a specific command
-                CustomisedHelpData::SingleCommand { command } => 
-                    e.title(format!("{:#?}", command.usage.unwrap()))
-                    .description("To view help for all categories and commands, type `!help`\nTo view help for a category or command, type `!help [command/category]`")
-                    .color(rand_color())
-                    .field("Description", format!("{:#?}", command.description.unwrap().replace("\n", "").replace("\"", "")), false)
-                    .field("Category", command.group_name, false),
+                CustomisedHelpData::SingleCommand { command } =>
+                    command_embed(e, command.usage.unwrap_or(command.name), command.description.unwrap_or_default(), command.group_name, &prefix),
 
                 // When no command matches or comes close to user's query
-                CustomisedHelpData::NoCommandFound { help_error_message } => e.description(help_error_message)
+                CustomisedHelpData::NoCommandFound { help_error_message } => e.description(with_prefix(help_error_message, &prefix))
                     .color(Colour::RED)
                     .footer(|f| 
                         f.text(format!("Requested by {}", msg.author.name))
@@ -88,4 +79,89 @@ async fn help(
         })
     }).await;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Lists every command by group, for `!help` and `/help` with no arguments
+fn grouped_embed<'a>(e: &'a mut CreateEmbed, help_description: &str, groups: &[(&str, Vec<String>)], prefix: &str, author: &User) -> &'a mut CreateEmbed {
+    e.title("**Bot Commands**")
+    .description(with_prefix(help_description, prefix))
+    .color(rand_color())
+    .footer(|f|
+        f.text(format!("Requested by {}", author.name))
+        .icon_url(author.face())
+        );
+
+    for (name, command_names) in groups {
+        e.field(name, command_names.join("\n"), true);
+    }
+
+    e
+}
+
+/// Describes a single command, for `!help <command>` and `/help <command>`
+fn command_embed<'a>(e: &'a mut CreateEmbed, usage: &str, description: &str, group_name: &str, prefix: &str) -> &'a mut CreateEmbed {
+    e.title(format!("{:#?}", with_prefix(usage, prefix)))
+    .description(with_prefix("To view help for all categories and commands, type `!help`\nTo view help for a category or command, type `!help [command/category]`", prefix))
+    .color(rand_color())
+    .field("Description", format!("{:#?}", description.replace(['\n', '"'], "")), false)
+    .field("Category", group_name, false)
+}
+
+/// `/help`, built from the same command groups as `!help`. Owner-only groups are left out for everyone else.
+pub async fn slash_help(ctx: &Context, user: &User, guild_id: Option<GuildId>, query: Option<&str>) -> Response {
+    let prefix = guild_settings::prefix(ctx, guild_id).await;
+
+    let is_owner = {
+        let data = ctx.data.read().await;
+        let config = data.get::<ConfigContainer>().unwrap().read().await;
+        config.owners.contains(&user.id)
+    };
+
+    let visible = GROUPS.iter().filter(|group| group.options.help_available && (is_owner || !group.options.owners_only));
+
+    let query = match query.map(str::trim) {
+        Some(query) if !query.is_empty() => query.trim_start_matches(prefix.as_str()).to_lowercase(),
+        _ => {
+            let groups: Vec<_> = visible
+                .map(|group| {
+                    let names = group.options.commands.iter()
+                        .filter(|command| command.options.help_available)
+                        .map(|command| format!("`{}{}`", prefix, command.options.names[0]))
+                        .collect();
+
+                    (group.name, names)
+                })
+                .collect();
+
+            return Response::embed(|e| grouped_embed(e, HELP.options.individual_command_tip, &groups, &prefix, user));
+        },
+    };
+
+    for group in visible {
+        for command in group.options.commands {
+            if command.options.names.iter().any(|name| name.eq_ignore_ascii_case(&query)) {
+                let options = command.options;
+                return Response::embed(|e| command_embed(e, options.usage.unwrap_or(options.names[0]), options.desc.unwrap_or_default(), group.name, &prefix));
+            }
+        }
+    }
+
+    Response::embed(|e|
+        e.description(with_prefix(&HELP.options.command_not_found_text.replace("{}", &query), &prefix))
+            .color(Colour::RED)
+            .footer(|f|
+                f.text(format!("Requested by {}", user.name))
+                .icon_url(user.face())
+            )
+    )
+}
+
+/// Usage strings and tips are written with the default `!` prefix. This swaps it for the guild's own.
+fn with_prefix(text: &str, prefix: &str) -> String {
+    let text = text.replace("`!", &format!("`{}", prefix));
+
+    match text.strip_prefix('!') {
+        Some(rest) => format!("{}{}", prefix, rest),
+        None => text,
+    }
+}
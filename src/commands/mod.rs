@@ -1,4 +1,18 @@
+pub mod admin;
 pub mod help;
 pub mod moderation;
 pub mod music;
-pub mod utility;
\ No newline at end of file
+pub mod settings;
+pub mod slash;
+pub mod utility;
+
+use serenity::framework::standard::CommandGroup;
+
+/// Every command group, in the order help lists them
+pub static GROUPS: &[&CommandGroup] = &[
+    &utility::UTILITIES_GROUP, // src/commands/utility.rs
+    &music::MUSIC_GROUP, // src/commands/music/mod.rs
+    &moderation::MODERATION_GROUP, // src/commands/moderation.rs
+    &settings::SETTINGS_GROUP, // src/commands/settings.rs
+    &admin::ADMIN_GROUP, // src/commands/admin.rs
+];
@@ -0,0 +1,684 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serenity::builder::{EditChannel, EditMember};
+use serenity::framework::standard::{Args, CommandOptions, CommandResult, Reason};
+use serenity::framework::standard::macros::{group, command, check};
+use serenity::json::hashmap_to_json_map;
+use serenity::model::channel::Message;
+use serenity::model::Permissions;
+use serenity::model::prelude::{GuildId, MessageId, RoleId, Timestamp, UserId};
+use serenity::prelude::*;
+
+use crate::database::connection::DatabaseContainer;
+use crate::database::guild_settings;
+use crate::database::warnings::{EscalationAction, EscalationRule};
+use crate::utils::embeds::action_embed;
+use crate::utils::modlog::{record_case, refresh_case, MAX_REASON_LENGTH};
+use crate::utils::utilities::{duration_formatter, duration_parser};
+
+
+#[group]
+#[commands(kick, ban, unban, timeout, purge, slowmode, role, warn, warnings, delwarn, clearwarns, escalation, reason)]
+struct Moderation;
+
+/// Longest timeout Discord allows
+const MAX_TIMEOUT: Duration = Duration::from_secs(28 * 86400);
+
+/// Longest slowmode Discord allows
+const MAX_SLOWMODE: Duration = Duration::from_secs(6 * 3600);
+
+/// Discord only bulk deletes messages younger than two weeks. A minute of slack avoids racing the cutoff.
+const BULK_DELETE_MAX_AGE: i64 = 14 * 86400 - 60;
+
+
+// `#[required_permissions]` predates timeouts and doesn't know `MODERATE_MEMBERS`, so it's checked here instead
+#[check]
+#[name = "ModerateMembers"]
+async fn moderate_members_check(ctx: &Context, msg: &Message, _: &mut Args, _: &CommandOptions) -> Result<(), Reason> {
+    let permissions = match msg.member(ctx).await {
+        Ok(member) => member.permissions(&ctx.cache).ok(),
+        Err(_) => None,
+    };
+
+    match permissions {
+        Some(permissions) if permissions.contains(Permissions::MODERATE_MEMBERS) => Ok(()),
+        _ => Err(Reason::User("You need the `Moderate Members` permission to use this command".to_string())),
+    }
+}
+
+/// Audit log entries name the bot as the one acting, so the moderator is written into the reason
+fn audit_reason(msg: &Message, reason: &str) -> String {
+    let full = format!("{}: {}", msg.author.tag(), given_reason(reason));
+
+    full.chars().take(512).collect()
+}
+
+fn given_reason(reason: &str) -> &str {
+    if reason.trim().is_empty() { "No reason given" } else { reason.trim() }
+}
+
+/// Replies and returns `false` when `target` is the author, the bot, not a member, or not below the author in the role hierarchy.
+/// Bans also work on users who aren't in the server, so `allow_non_member` lets those through.
+async fn check_target(ctx: &Context, msg: &Message, target: UserId, allow_non_member: bool) -> serenity::Result<bool> {
+    let guild_id = msg.guild_id.unwrap();
+
+    let problem = if target == msg.author.id {
+        Some("You can't use this on yourself")
+    } else if target == ctx.cache.current_user_id() {
+        Some("I can't use this on myself")
+    } else {
+        // Falls back to HTTP so members missing from the cache still get the hierarchy check
+        match guild_id.member(ctx, target).await {
+            Ok(member) => {
+                let owner_id = ctx.cache.guild_field(guild_id, |guild| guild.owner_id);
+                let author_position = msg.member(ctx).await?.highest_role_info(&ctx.cache).map_or(0, |(_, position)| position);
+                let target_position = member.highest_role_info(&ctx.cache).map_or(0, |(_, position)| position);
+
+                if owner_id == Some(target) || (owner_id != Some(msg.author.id) && target_position >= author_position) {
+                    Some("That user's top role is not below yours")
+                } else {
+                    None
+                }
+            },
+            Err(serenity::Error::Http(why)) if why.status_code().is_some_and(|status| status.as_u16() == 404) => {
+                if allow_non_member { None } else { Some("That user is not a member of this server") }
+            },
+            Err(why) => return Err(why),
+        }
+    };
+
+    if let Some(problem) = problem {
+        msg.channel_id.say(ctx, problem).await?;
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+async fn confirm(ctx: &Context, msg: &Message, title: &str, description: String) -> CommandResult {
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e| action_embed(e, title, description, &msg.author))
+    ).await?;
+
+    Ok(())
+}
+
+/// Records the action as a case, posting it to the mod-log, then confirms it with the case number in the title
+async fn confirm_case(ctx: &Context, msg: &Message, action: &str, target: String, reason: &str, title: &str, description: String) -> CommandResult {
+    let case = record_case(ctx, msg.guild_id.unwrap(), &msg.author, action, target, given_reason(reason)).await?;
+
+    confirm(ctx, msg, &format!("{} | Case #{}", title, case.case_id), description).await
+}
+
+/// Times a member out for `duration`, or lifts their timeout when it is `None`
+async fn apply_timeout(ctx: &Context, guild_id: GuildId, user_id: UserId, duration: Option<Duration>, reason: &str) -> CommandResult {
+    let mut edit = EditMember::default();
+
+    match duration {
+        Some(duration) => {
+            let until = Timestamp::from_unix_timestamp(Utc::now().timestamp() + duration.as_secs() as i64)?;
+            edit.disable_communication_until_datetime(until);
+        },
+        None => { edit.enable_communication(); },
+    }
+
+    ctx.http.edit_member(guild_id.0, user_id.0, &hashmap_to_json_map(edit.0), Some(reason)).await?;
+
+    Ok(())
+}
+
+async fn parse_user(ctx: &Context, msg: &Message, args: &mut Args) -> serenity::Result<Option<UserId>> {
+    match args.single::<UserId>() {
+        Ok(user_id) => Ok(Some(user_id)),
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please mention a user or give their ID").await?;
+            Ok(None)
+        },
+    }
+}
+
+
+#[command]
+#[description = "Kicks a member from the server"]
+#[usage = "!kick <user> [reason]"]
+#[only_in(guilds)]
+#[required_permissions("KICK_MEMBERS")]
+#[min_args(1)]
+async fn kick(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    if !check_target(ctx, msg, user_id, false).await? {
+        return Ok(());
+    }
+
+    let reason = audit_reason(msg, args.rest());
+    msg.guild_id.unwrap().kick_with_reason(ctx, user_id, &reason).await?;
+
+    confirm_case(ctx, msg, "Kick", format!("<@{}>", user_id.0), args.rest(),
+        "Member Kicked", format!("Kicked <@{}>\n**Reason:** {}", user_id.0, given_reason(args.rest()))).await
+}
+
+#[command]
+#[description = "Bans a user from the server"]
+#[usage = "!ban <user> [reason]"]
+#[only_in(guilds)]
+#[required_permissions("BAN_MEMBERS")]
+#[min_args(1)]
+async fn ban(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    if !check_target(ctx, msg, user_id, true).await? {
+        return Ok(());
+    }
+
+    let reason = audit_reason(msg, args.rest());
+    msg.guild_id.unwrap().ban_with_reason(ctx, user_id, 0, &reason).await?;
+
+    confirm_case(ctx, msg, "Ban", format!("<@{}>", user_id.0), args.rest(),
+        "User Banned", format!("Banned <@{}>\n**Reason:** {}", user_id.0, given_reason(args.rest()))).await
+}
+
+#[command]
+#[description = "Lifts a user's ban"]
+#[usage = "!unban <user ID> [reason]"]
+#[only_in(guilds)]
+#[required_permissions("BAN_MEMBERS")]
+#[min_args(1)]
+async fn unban(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    let reason = audit_reason(msg, args.rest());
+    ctx.http.remove_ban(msg.guild_id.unwrap().0, user_id.0, Some(&reason)).await?;
+
+    confirm_case(ctx, msg, "Unban", format!("<@{}>", user_id.0), args.rest(),
+        "User Unbanned", format!("Unbanned <@{}>\n**Reason:** {}", user_id.0, given_reason(args.rest()))).await
+}
+
+#[command]
+#[description = "Times out a member so they can't talk or react. Use `off` to lift a timeout."]
+#[usage = "!timeout <user> <duration (e.g. 10m, 1h30m)/off> [reason]"]
+#[only_in(guilds)]
+#[checks(ModerateMembers)]
+#[min_args(2)]
+async fn timeout(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    if !check_target(ctx, msg, user_id, false).await? {
+        return Ok(());
+    }
+
+    let length = args.single::<String>()?;
+
+    let duration = if length.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        match duration_parser(&length) {
+            Some(duration) if !duration.is_zero() && duration <= MAX_TIMEOUT => Some(duration),
+            _ => {
+                msg.channel_id.say(ctx, "Duration must be between `1s` and `28d` (e.g. `10m` or `1h30m`)").await?;
+                return Ok(());
+            },
+        }
+    };
+
+    let reason = audit_reason(msg, args.rest());
+    apply_timeout(ctx, msg.guild_id.unwrap(), user_id, duration, &reason).await?;
+
+    let (action, title, description) = match duration {
+        Some(duration) => ("Timeout", "Member Timed Out", format!("Timed out <@{}> for {}", user_id.0, duration_formatter(duration))),
+        None => ("Timeout Lifted", "Timeout Lifted", format!("Lifted the timeout on <@{}>", user_id.0)),
+    };
+
+    confirm_case(ctx, msg, action, format!("<@{}>", user_id.0), args.rest(),
+        title, format!("{}\n**Reason:** {}", description, given_reason(args.rest()))).await
+}
+
+#[command]
+#[description = "Bulk deletes recent messages in this channel, optionally only those from one user. Messages older than two weeks are skipped."]
+#[usage = "!purge <count 1-100> [user]"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_MESSAGES")]
+#[min_args(1)]
+async fn purge(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let count = match args.single::<usize>() {
+        Ok(count) if (1..=100).contains(&count) => count,
+        _ => {
+            msg.channel_id.say(ctx, "Please enter a number of messages between `1-100`").await?;
+            return Ok(());
+        },
+    };
+
+    let user_filter = if args.is_empty() {
+        None
+    } else {
+        match parse_user(ctx, msg, &mut args).await? {
+            Some(user_id) => Some(user_id),
+            None => return Ok(()),
+        }
+    };
+
+    // When filtering by user, look further back so there is enough to pick from
+    let fetch = if user_filter.is_some() { 100 } else { count as u64 };
+    let messages = msg.channel_id.messages(ctx, |r| r.before(msg.id).limit(fetch)).await?;
+    let cutoff = Utc::now().timestamp() - BULK_DELETE_MAX_AGE;
+
+    let ids: Vec<MessageId> = messages.iter()
+        .filter(|m| user_filter.is_none_or(|user_id| m.author.id == user_id))
+        .filter(|m| m.timestamp.unix_timestamp() > cutoff)
+        .take(count)
+        .map(|m| m.id)
+        .collect();
+
+    match ids.len() {
+        0 => {},
+        1 => msg.channel_id.delete_message(ctx, ids[0]).await?,
+        _ => msg.channel_id.delete_messages(ctx, &ids).await?,
+    }
+
+    msg.delete(ctx).await?;
+
+    let description = match user_filter {
+        Some(user_id) => format!("Deleted {} message(s) from <@{}>", ids.len(), user_id.0),
+        None => format!("Deleted {} message(s)", ids.len()),
+    };
+
+    confirm_case(ctx, msg, "Purge", format!("<#{}>", msg.channel_id.0), &description, "Messages Purged", description.clone()).await
+}
+
+#[command]
+#[description = "Sets how long members must wait between messages in this channel. Use `off` to disable."]
+#[usage = "!slowmode <duration (e.g. 5s, 1m)/off> [reason]"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_CHANNELS")]
+#[min_args(1)]
+async fn slowmode(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let length = args.single::<String>()?;
+
+    let duration = if length.eq_ignore_ascii_case("off") {
+        Duration::ZERO
+    } else {
+        match duration_parser(&length) {
+            Some(duration) if duration <= MAX_SLOWMODE => duration,
+            _ => {
+                msg.channel_id.say(ctx, "Slowmode must be between `0s` and `6h` (e.g. `5s` or `1m`)").await?;
+                return Ok(());
+            },
+        }
+    };
+
+    let mut edit = EditChannel::default();
+    edit.rate_limit_per_user(duration.as_secs());
+
+    let reason = audit_reason(msg, args.rest());
+    ctx.http.edit_channel(msg.channel_id.0, &hashmap_to_json_map(edit.0), Some(&reason)).await?;
+
+    let description = if duration.is_zero() {
+        format!("Disabled slowmode in <#{}>", msg.channel_id.0)
+    } else {
+        format!("Set slowmode in <#{}> to {}", msg.channel_id.0, duration_formatter(duration))
+    };
+
+    confirm_case(ctx, msg, "Slowmode", format!("<#{}>", msg.channel_id.0), args.rest(),
+        "Slowmode Updated", format!("{}\n**Reason:** {}", description, given_reason(args.rest()))).await
+}
+
+#[command]
+#[description = "Adds or removes a role from a member"]
+#[usage = "!role <add/remove> <user> <role> [reason]"]
+#[sub_commands(role_add, role_remove)]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_ROLES")]
+async fn role(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id.say(ctx, "Please choose `add` or `remove`, e.g. `role add @user @role`").await?;
+
+    Ok(())
+}
+
+#[command("add")]
+#[description = "Gives a member a role"]
+#[usage = "!role add <user> <role> [reason]"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_ROLES")]
+#[min_args(2)]
+async fn role_add(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    change_role(ctx, msg, args, true).await
+}
+
+#[command("remove")]
+#[description = "Takes a role away from a member"]
+#[usage = "!role remove <user> <role> [reason]"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_ROLES")]
+#[min_args(2)]
+async fn role_remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    change_role(ctx, msg, args, false).await
+}
+
+async fn change_role(ctx: &Context, msg: &Message, mut args: Args, add: bool) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    if !check_target(ctx, msg, user_id, false).await? {
+        return Ok(());
+    }
+
+    // Roles can be given as a mention, an ID, or a name (quoted if it has spaces)
+    let role_arg = args.single_quoted::<String>()?;
+    let guild = msg.guild(&ctx.cache).unwrap();
+
+    let role = match role_arg.parse::<RoleId>() {
+        Ok(role_id) => guild.roles.get(&role_id),
+        Err(_) => guild.role_by_name(&role_arg),
+    };
+
+    let role = match role {
+        Some(role) => role.clone(),
+        None => {
+            msg.channel_id.say(ctx, format!("There is no role called `{}`", role_arg)).await?;
+            return Ok(());
+        },
+    };
+
+    let author_position = guild.members.get(&msg.author.id)
+        .and_then(|member| member.highest_role_info(&ctx.cache))
+        .map_or(0, |(_, position)| position);
+
+    if msg.author.id != guild.owner_id && role.position >= author_position {
+        msg.channel_id.say(ctx, "That role is not below your top role").await?;
+        return Ok(());
+    }
+
+    let reason = audit_reason(msg, args.rest());
+
+    if add {
+        ctx.http.add_member_role(guild.id.0, user_id.0, role.id.0, Some(&reason)).await?;
+        confirm_case(ctx, msg, "Role Added", format!("<@{}>", user_id.0), args.rest(),
+            "Role Added", format!("Gave <@{}> the <@&{}> role\n**Reason:** {}", user_id.0, role.id.0, given_reason(args.rest()))).await
+    } else {
+        ctx.http.remove_member_role(guild.id.0, user_id.0, role.id.0, Some(&reason)).await?;
+        confirm_case(ctx, msg, "Role Removed", format!("<@{}>", user_id.0), args.rest(),
+            "Role Removed", format!("Took the <@&{}> role from <@{}>\n**Reason:** {}", role.id.0, user_id.0, given_reason(args.rest()))).await
+    }
+}
+
+
+#[command]
+#[description = "Gives a member a warning. Reaching a warning count with an escalation rule applies it automatically."]
+#[usage = "!warn <user> [reason]"]
+#[only_in(guilds)]
+#[checks(ModerateMembers)]
+#[min_args(1)]
+async fn warn(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    if !check_target(ctx, msg, user_id, false).await? {
+        return Ok(());
+    }
+
+    let guild_id = msg.guild_id.unwrap();
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    let previous = database.warnings(guild_id, user_id)?.len();
+    let id = database.add_warning(guild_id, user_id, msg.author.id, given_reason(args.rest()))?;
+    let count = database.warnings(guild_id, user_id)?.len();
+
+    confirm_case(ctx, msg, "Warn", format!("<@{}>", user_id.0), args.rest(), "Member Warned", format!(
+        "Warned <@{}> (warning `#{}`, {} in total)\n**Reason:** {}", user_id.0, id, count, given_reason(args.rest())
+    )).await?;
+
+    // The strongest rule this warning reached. Rules passed by earlier warnings were applied back then.
+    let rule = database.escalation_rules(guild_id)?
+        .into_iter()
+        .filter(|rule| rule.warnings as usize > previous && rule.warnings as usize <= count)
+        .max_by_key(|rule| rule.warnings);
+
+    if let Some(rule) = rule {
+        let reason = audit_reason(msg, &format!("Automatic escalation after {} warnings", count));
+
+        let (action, description) = match rule.action {
+            EscalationAction::Timeout(duration) => {
+                apply_timeout(ctx, guild_id, user_id, Some(duration), &reason).await?;
+                ("Timeout", format!("Timed out <@{}> for {}", user_id.0, duration_formatter(duration)))
+            },
+            EscalationAction::Kick => {
+                guild_id.kick_with_reason(ctx, user_id, &reason).await?;
+                ("Kick", format!("Kicked <@{}>", user_id.0))
+            },
+            EscalationAction::Ban => {
+                guild_id.ban_with_reason(ctx, user_id, 0, &reason).await?;
+                ("Ban", format!("Banned <@{}>", user_id.0))
+            },
+        };
+
+        let escalation_reason = format!("Automatic escalation after {} warnings", count);
+
+        confirm_case(ctx, msg, action, format!("<@{}>", user_id.0), &escalation_reason,
+            "Warning Escalated", format!("{}\n**Reason:** Reached {} warnings", description, count)).await?;
+    }
+
+    Ok(())
+}
+
+#[command]
+#[description = "Lists a member's warnings"]
+#[usage = "!warnings <user>"]
+#[only_in(guilds)]
+#[checks(ModerateMembers)]
+#[min_args(1)]
+async fn warnings(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let warnings = database.warnings(msg.guild_id.unwrap(), user_id)?;
+
+    if warnings.is_empty() {
+        return confirm(ctx, msg, "Warnings", format!("<@{}> has no warnings", user_id.0)).await;
+    }
+
+    // Keep well under the embed description limit by showing the most recent ones
+    let lines: Vec<String> = warnings.iter()
+        .rev()
+        .take(15)
+        .map(|warning| format!(
+            "**#{}** <t:{}:R> by <@{}>\n{}", warning.id, warning.created_at, warning.moderator_id.0, warning.reason
+        ))
+        .collect();
+
+    confirm(ctx, msg, "Warnings", format!(
+        "<@{}> has {} warning(s)\n\n{}", user_id.0, warnings.len(), lines.join("\n")
+    )).await
+}
+
+#[command]
+#[description = "Deletes a warning by its ID"]
+#[usage = "!delwarn <id>"]
+#[only_in(guilds)]
+#[checks(ModerateMembers)]
+#[min_args(1)]
+async fn delwarn(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let id = match args.single::<String>()?.trim_start_matches('#').parse::<i64>() {
+        Ok(id) => id,
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please enter a warning ID (e.g. `12`)").await?;
+            return Ok(());
+        },
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    match database.delete_warning(msg.guild_id.unwrap(), id)? {
+        Some(warning) => confirm_case(ctx, msg, "Warning Deleted", format!("<@{}>", warning.user_id.0), &format!("Deleted warning #{}: {}", warning.id, warning.reason), "Warning Deleted", format!(
+            "Deleted warning `#{}` from <@{}>\n**Reason was:** {}", warning.id, warning.user_id.0, warning.reason
+        )).await,
+        None => {
+            msg.channel_id.say(ctx, format!("There is no warning with ID `{}`", id)).await?;
+            Ok(())
+        },
+    }
+}
+
+#[command]
+#[description = "Deletes all of a member's warnings"]
+#[usage = "!clearwarns <user>"]
+#[only_in(guilds)]
+#[checks(ModerateMembers)]
+#[min_args(1)]
+async fn clearwarns(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = match parse_user(ctx, msg, &mut args).await? {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let cleared = database.clear_warnings(msg.guild_id.unwrap(), user_id)?;
+
+    confirm_case(ctx, msg, "Warnings Cleared", format!("<@{}>", user_id.0), &format!("Cleared {} warning(s)", cleared),
+        "Warnings Cleared", format!("Cleared {} warning(s) from <@{}>", cleared, user_id.0)).await
+}
+
+#[command]
+#[description = "Lists the actions taken automatically when a member reaches a number of warnings"]
+#[usage = "!escalation [set/remove]"]
+#[sub_commands(escalation_set, escalation_remove)]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn escalation(ctx: &Context, msg: &Message) -> CommandResult {
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let rules = database.escalation_rules(msg.guild_id.unwrap())?;
+
+    let description = if rules.is_empty() {
+        let prefix = guild_settings::prefix(ctx, msg.guild_id).await;
+        format!("No escalation rules are set. Add one with `{}escalation set <warnings> <timeout <duration>/kick/ban>`", prefix)
+    } else {
+        rules.iter()
+            .map(|rule| format!("**{} warnings:** {}", rule.warnings, describe_action(rule.action)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    confirm(ctx, msg, "Escalation Rules", description).await
+}
+
+#[command("set")]
+#[description = "Sets what happens when a member reaches a number of warnings"]
+#[usage = "!escalation set <warnings> <timeout <duration>/kick/ban>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(2)]
+async fn escalation_set(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let warnings = match args.single::<u32>() {
+        Ok(warnings) if warnings >= 1 => warnings,
+        _ => {
+            msg.channel_id.say(ctx, "Please enter a warning count of at least `1`").await?;
+            return Ok(());
+        },
+    };
+
+    let action = match args.single::<String>()?.to_lowercase().as_str() {
+        "kick" => EscalationAction::Kick,
+        "ban" => EscalationAction::Ban,
+        "timeout" => match args.single::<String>().ok().as_deref().and_then(duration_parser) {
+            Some(duration) if !duration.is_zero() && duration <= MAX_TIMEOUT => EscalationAction::Timeout(duration),
+            _ => {
+                msg.channel_id.say(ctx, "Timeouts need a duration between `1s` and `28d` (e.g. `timeout 1h`)").await?;
+                return Ok(());
+            },
+        },
+        _ => {
+            msg.channel_id.say(ctx, "The action must be `timeout <duration>`, `kick` or `ban`").await?;
+            return Ok(());
+        },
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_escalation_rule(msg.guild_id.unwrap(), EscalationRule { warnings, action })?;
+
+    confirm(ctx, msg, "Escalation Rule Set", format!("At **{} warnings:** {}", warnings, describe_action(action))).await
+}
+
+#[command("remove")]
+#[description = "Removes the escalation rule for a number of warnings"]
+#[usage = "!escalation remove <warnings>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(1)]
+async fn escalation_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let warnings = args.single::<u32>()?;
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    if database.remove_escalation_rule(msg.guild_id.unwrap(), warnings)? {
+        confirm(ctx, msg, "Escalation Rule Removed", format!("Removed the rule for {} warnings", warnings)).await
+    } else {
+        msg.channel_id.say(ctx, format!("There is no rule for {} warnings", warnings)).await?;
+        Ok(())
+    }
+}
+
+fn describe_action(action: EscalationAction) -> String {
+    match action {
+        EscalationAction::Timeout(duration) => format!("Timeout for {}", duration_formatter(duration)),
+        EscalationAction::Kick => "Kick".to_string(),
+        EscalationAction::Ban => "Ban".to_string(),
+    }
+}
+
+#[command]
+#[description = "Changes the reason on a moderation case and updates its mod-log entry"]
+#[usage = "!reason <case> <reason>"]
+#[only_in(guilds)]
+#[checks(ModerateMembers)]
+#[min_args(2)]
+async fn reason(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let case_id = match args.single::<String>()?.trim_start_matches('#').parse::<i64>() {
+        Ok(case_id) => case_id,
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please enter a case number (e.g. `12`)").await?;
+            return Ok(());
+        },
+    };
+
+    let new_reason = args.rest().trim();
+
+    if new_reason.chars().count() > MAX_REASON_LENGTH {
+        msg.channel_id.say(ctx, format!("The reason can be at most {} characters long", MAX_REASON_LENGTH)).await?;
+        return Ok(());
+    }
+
+    let guild_id = msg.guild_id.unwrap();
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    let mut case = match database.case(guild_id, case_id)? {
+        Some(case) => case,
+        None => {
+            msg.channel_id.say(ctx, format!("There is no case `#{}`", case_id)).await?;
+            return Ok(());
+        },
+    };
+
+    case.reason = new_reason.to_string();
+    database.set_case_reason(guild_id, case_id, &case.reason)?;
+    refresh_case(ctx, guild_id, &case).await?;
+
+    confirm(ctx, msg, "Case Updated", format!("Updated the reason for case `#{}`\n**Reason:** {}", case_id, case.reason)).await
+}
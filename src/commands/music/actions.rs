@@ -0,0 +1,1184 @@
+//! What each music command does, shared by the prefix and slash versions so they can't drift apart.
+//! Each action returns the reply to send instead of sending it.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use rand::seq::SliceRandom;
+use serenity::builder::CreateEmbed;
+use serenity::client::Context;
+use serenity::framework::standard::CommandResult;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::channel::{Attachment, Message};
+use serenity::model::prelude::{ChannelId, GuildId, User, UserId};
+use serenity::prelude::{Mentionable, Mutex};
+use serenity::utils::Colour;
+
+use songbird::input::Metadata;
+use songbird::tracks::{self, LoopState, PlayMode, TrackHandle};
+use songbird::{Call, Event, TrackEvent};
+
+use super::{permissions, player, TrackEndNotifier};
+use crate::config::bot_config::ConfigContainer;
+use crate::database::connection::DatabaseContainer;
+use crate::utils::audio::{self, AudioFilter, SourceTarget, Upload};
+use crate::utils::embeds::action_embed;
+use crate::utils::music_sessions::{LoopMode, MusicSession, MusicSessions};
+use crate::utils::response::Response;
+use crate::utils::{track_display, ytdlp};
+use crate::utils::track_requests::{self, RequestSource, TrackRequest};
+use crate::utils::utilities::{duration_formatter, num_prefix};
+
+
+/// Tracks listed on each page of `queue`
+pub const QUEUE_PAGE_SIZE: usize = 10;
+
+/// Custom IDs of the queue page buttons are this followed by the page to show
+pub const QUEUE_BUTTON_PREFIX: &str = "queue:";
+
+
+/// Who ran a music command and where, whichever way it was run
+pub struct Invocation<'a> {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub user: &'a User,
+    /// Whether it came from a slash command or button rather than a message
+    pub interaction: bool,
+}
+
+impl<'a> Invocation<'a> {
+    /// Only for commands marked `only_in(guilds)`
+    pub fn from_message(msg: &'a Message) -> Invocation<'a> {
+        Invocation { guild_id: msg.guild_id.unwrap(), channel_id: msg.channel_id, user: &msg.author, interaction: false }
+    }
+
+    /// `None` outside of guilds
+    pub fn from_interaction(interaction: &'a ApplicationCommandInteraction) -> Option<Invocation<'a>> {
+        Some(Invocation { guild_id: interaction.guild_id?, channel_id: interaction.channel_id, user: &interaction.user, interaction: true })
+    }
+
+    /// `None` outside of guilds
+    pub fn from_component(interaction: &'a MessageComponentInteraction) -> Option<Invocation<'a>> {
+        Some(Invocation { guild_id: interaction.guild_id?, channel_id: interaction.channel_id, user: &interaction.user, interaction: true })
+    }
+
+    /// How the `command` being run is recorded on the tracks it queues
+    pub fn request_source(&self, command: &'static str) -> RequestSource {
+        if self.interaction {
+            RequestSource::SlashCommand(command)
+        } else {
+            RequestSource::PrefixCommand(command)
+        }
+    }
+}
+
+
+/// Joins the invoker's voice channel, starting a music session there if there wasn't one.
+/// The error is the reply to send when that isn't possible.
+async fn connect(ctx: &Context, invocation: &Invocation<'_>) -> Result<(Arc<Mutex<Call>>, ChannelId), Response> {
+    let voice_channel = ctx.cache
+        .guild_field(invocation.guild_id, |guild| {
+            guild.voice_states.get(&invocation.user.id).and_then(|voice_state| voice_state.channel_id)
+        })
+        .flatten();
+
+    let connect_to = match voice_channel {
+        Some(channel) => channel,
+        None => return Err(Response::text("Not in a voice channel")),
+    };
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+
+    // Calls are kept after leaving, along with their event handlers, so only add those to new ones
+    let is_new = manager.get(invocation.guild_id).is_none();
+    let (handle_lock, success) = manager.join(invocation.guild_id, connect_to).await;
+
+    if success.is_err() {
+        return Err(Response::text("Error joining the channel"));
+    }
+
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+    sessions.lock().unwrap().entry(invocation.guild_id).or_insert_with(|| MusicSession::new(invocation.channel_id));
+
+    if is_new {
+        handle_lock.lock().await.add_global_event(
+            Event::Track(TrackEvent::End),
+            TrackEndNotifier {
+                chan_id: invocation.channel_id,
+                context: ctx.clone(),
+                guild: invocation.guild_id
+            },
+        );
+    }
+
+    Ok((handle_lock, connect_to))
+}
+
+/// Everyone other than bots in `channel_id`, as far as the cache knows
+pub fn listeners(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Vec<UserId> {
+    ctx.cache
+        .guild_field(guild_id, |guild| {
+            guild.voice_states.values()
+                .filter(|voice_state| voice_state.channel_id == Some(channel_id))
+                .filter(|voice_state| {
+                    let is_bot = match &voice_state.member {
+                        Some(member) => member.user.bot,
+                        None => guild.members.get(&voice_state.user_id).is_some_and(|member| member.user.bot),
+                    };
+
+                    !is_bot
+                })
+                .map(|voice_state| voice_state.user_id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The guild's call, if the bot is in one
+async fn call(ctx: &Context, guild_id: GuildId) -> Option<Arc<Mutex<Call>>> {
+    songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .get(guild_id)
+}
+
+
+pub async fn join(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    match connect(ctx, invocation).await {
+        Ok((_, channel)) => Ok(Response::text(format!("Joined {}", channel.mention()))),
+        Err(response) => Ok(response),
+    }
+}
+
+pub async fn leave(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client placed in at initialisation.")
+        .clone();
+
+    let channel = match manager.get(invocation.guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.current_channel(),
+        None => None,
+    };
+
+    let channel = match channel {
+        Some(channel) => channel,
+        None => return Ok(Response::text("Not in a voice channel")),
+    };
+
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+    let session = sessions.lock().unwrap().remove(&invocation.guild_id);
+
+    if let Some(session) = session {
+        let mut embed = CreateEmbed::default();
+        action_embed(&mut embed, "Left Voice", format!("Left <#{}>", channel.0), invocation.user);
+        session.close_player(&ctx.http, embed).await;
+    }
+
+    manager.remove(invocation.guild_id).await?;
+
+    Ok(Response::text(format!("Successfully left <#{}>", channel.0)))
+}
+
+pub async fn pause(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Not in a voice channel")),
+    };
+
+    let current_track = match handler_lock.lock().await.queue().current() {
+        Some(track) => track,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let info = current_track.get_info().await?;
+
+    if matches!(info.playing, PlayMode::Pause) {
+        return Ok(Response::text("Already paused"));
+    }
+
+    current_track.pause()?;
+    player::refresh(ctx, invocation.guild_id).await;
+
+    let metadata = current_track.metadata();
+    Ok(Response::text(format!("Paused **{}**\n{}", track_display::title(metadata), track_display::progress_bar(info.position, metadata.duration))))
+}
+
+pub async fn resume(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("No music to resume")),
+    };
+
+    let current_track = match handler_lock.lock().await.queue().current() {
+        Some(track) => track,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let info = current_track.get_info().await?;
+
+    if !matches!(info.playing, PlayMode::Pause) {
+        return Ok(Response::text("Not paused"));
+    }
+
+    current_track.play()?;
+    player::refresh(ctx, invocation.guild_id).await;
+
+    let metadata = current_track.metadata();
+    Ok(Response::text(format!("Resumed **{}**\n{}", track_display::title(metadata), track_display::progress_bar(info.position, metadata.duration))))
+}
+
+/// Plays `search` (a URL or search terms) or `attachment`, or resumes playback if given neither
+pub async fn play(ctx: &Context, invocation: &Invocation<'_>, search: &str, attachment: Option<&Attachment>) -> CommandResult<Response> {
+    let search = search.trim();
+
+    if search.is_empty() && attachment.is_none() {
+        return resume(ctx, invocation).await;
+    }
+
+    let (handler_lock, _) = match connect(ctx, invocation).await {
+        Ok(connected) => connected,
+        Err(response) => return Ok(response),
+    };
+
+    if attachment.is_none() && search.starts_with("http") && ytdlp::is_playlist(search) {
+        return play_playlist(ctx, invocation, handler_lock, search).await;
+    }
+
+    // Load the source before locking the call, yt-dlp can take a while
+    let mut overrides = None;
+    let audio = audio::settings(ctx, invocation.guild_id).await;
+
+    let target = if let Some(attachment) = attachment {
+        overrides = Some(attachment);
+        SourceTarget::File(Upload::save(attachment).await?)
+    } else if search.starts_with("http") {
+        SourceTarget::Url(search.to_string())
+    } else {
+        SourceTarget::Url(format!("ytsearch1:{}", search))
+    };
+
+    let effects = audio::effects(ctx, invocation.guild_id).await;
+    let mut input = audio::source(target.clone(), &effects).await?;
+
+    // ffmpeg only knows the file, so describe uploads by who sent them
+    if let Some(attachment) = overrides {
+        input.metadata.source_url.replace(attachment.url.clone());
+        input.metadata.channel.replace(invocation.user.name.clone());
+        input.metadata.title.replace(attachment.filename.clone());
+    }
+
+    let (mut song, handle) = tracks::create_player(input);
+    song.set_volume(audio.gain());
+    audio::set_track_source(&handle, target, effects).await;
+    let metadata = handle.metadata().clone();
+    track_requests::set_request(&handle, TrackRequest {
+        requester: invocation.user.id,
+        requested_at: Utc::now().timestamp(),
+        source: invocation.request_source("play"),
+    }).await;
+
+    let was_idle = {
+        let mut handler = handler_lock.lock().await;
+        let was_idle = handler.queue().current().is_none();
+        handler.enqueue(song);
+        was_idle
+    };
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    if !was_idle {
+        return Ok(Response::text(format!("Enqueued {}", track_display::compact(&metadata))));
+    }
+
+    ctx.online().await;
+
+    Ok(Response::text(format!("Playing {}", track_display::compact(&metadata))))
+}
+
+/// Queues the entries of the playlist at `url`, up to the configured limit, and sums them up in one reply.
+/// Entries only start yt-dlp when they come up, so even long playlists queue quickly.
+async fn play_playlist(ctx: &Context, invocation: &Invocation<'_>, handler_lock: Arc<Mutex<Call>>, url: &str) -> CommandResult<Response> {
+    let limit = ctx.data.read().await.get::<ConfigContainer>().unwrap().read().await.playlist_limit;
+
+    let playlist = match ytdlp::flat_playlist(url, Some(limit)).await {
+        Ok(playlist) => playlist,
+        Err(why) => {
+            println!("Could not list playlist {}: {:?}", url, why);
+            return Ok(Response::text("Could not load that playlist"));
+        },
+    };
+
+    let audio = audio::settings(ctx, invocation.guild_id).await;
+    let effects = audio::effects(ctx, invocation.guild_id).await;
+    let requested_at = Utc::now().timestamp();
+    let mut songs = Vec::new();
+
+    for entry in playlist.entries.iter().take(limit) {
+        let entry_url = match entry.playable_url() {
+            Some(entry_url) => entry_url,
+            None => continue,
+        };
+
+        let metadata = Metadata {
+            title: entry.title.clone(),
+            channel: entry.channel.clone().or_else(|| entry.uploader.clone()),
+            duration: entry.duration.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
+            source_url: Some(entry_url.clone()),
+            ..Default::default()
+        };
+
+        let target = SourceTarget::Url(entry_url);
+
+        let input = match audio::described_source(target.clone(), &effects, metadata).await {
+            Ok(input) => input,
+            Err(why) => {
+                println!("Could not queue an entry of playlist {}: {:?}", url, why);
+                continue;
+            },
+        };
+
+        let (mut song, handle) = tracks::create_player(input);
+        song.set_volume(audio.gain());
+        audio::set_track_source(&handle, target, effects.clone()).await;
+        track_requests::set_request(&handle, TrackRequest {
+            requester: invocation.user.id,
+            requested_at,
+            source: invocation.request_source("play"),
+        }).await;
+
+        songs.push(song);
+    }
+
+    if songs.is_empty() {
+        return Ok(Response::text("There's nothing in that playlist that can be played"));
+    }
+
+    let added = songs.len();
+    let durations = songs.iter().filter_map(|song| song.handle.metadata().duration).collect::<Vec<_>>();
+    let live = added - durations.len();
+    let total = durations.into_iter().sum::<Duration>();
+
+    let (was_idle, first_position) = {
+        let mut handler = handler_lock.lock().await;
+        let queued = handler.queue().len();
+
+        for song in songs {
+            handler.enqueue(song);
+        }
+
+        (queued == 0, queued)
+    };
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    if was_idle {
+        ctx.online().await;
+    }
+
+    let name = match (&playlist.title, &playlist.webpage_url) {
+        (Some(title), Some(link)) => format!("[{}]({})", title.replace(['[', ']'], ""), link),
+        (Some(title), None) => format!("**{}**", title),
+        _ => "the playlist".to_string(),
+    };
+
+    let mut description = format!("Added **{}** tracks from {}", added, name);
+
+    if live > 0 {
+        description.push_str(&format!(", {} of them live or of unknown length", live));
+    }
+
+    if let Some(count) = playlist.playlist_count.filter(|&count| count > limit) {
+        description.push_str(&format!("\nIt has {} tracks, but only the first {} can be queued at a time", count, limit));
+    }
+
+    Ok(Response::embed(|e| {
+        action_embed(e, "Enqueued Playlist", description, invocation.user)
+            .field("• Total length", track_display::clock(total), true);
+
+        if !was_idle {
+            e.field("• Starting at", format!("{} in queue", num_prefix(first_position)), true);
+        }
+
+        e
+    }))
+}
+
+/// Shows one page of the queue, `page` counting from 1
+pub async fn queue(ctx: &Context, invocation: &Invocation<'_>, page: usize) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Not playing any music right now")),
+    };
+
+    let queue = handler_lock.lock().await.queue().current_queue();
+
+    let now_playing = match queue.first() {
+        Some(track) => track,
+        None => return Ok(Response::text("Nothing in the queue")),
+    };
+
+    let waiting = &queue[1..];
+    let loop_mode = loop_mode(ctx, invocation.guild_id).await;
+    let pages = waiting.len().div_ceil(QUEUE_PAGE_SIZE).max(1);
+
+    if page == 0 || page > pages {
+        return Ok(Response::text(format!("There {} only {} page(s) in the queue", if pages == 1 { "is" } else { "are" }, pages)));
+    }
+
+    let metadata = now_playing.metadata();
+    let position = now_playing.get_info().await?.position;
+
+    let mut now_playing_text = format!("{}\n{}", track_display::linked_title(metadata), track_display::progress_bar(position, metadata.duration));
+
+    if let Some(request) = track_requests::request(now_playing).await {
+        now_playing_text.push_str(&format!("\nRequested by {}", request.describe()));
+    }
+
+    // Time until each track starts. Once a track of unknown length is in the way, nobody can say.
+    let mut until = metadata.duration.map(|duration| duration.saturating_sub(position));
+    let mut description = String::new();
+
+    for (i, track) in waiting.iter().enumerate() {
+        let metadata = track.metadata();
+
+        if i / QUEUE_PAGE_SIZE == page - 1 {
+            description.push_str(&track_display::queue_line(i + 1, metadata));
+
+            if let Some(request) = track_requests::request(track).await {
+                description.push_str(&format!(" • {}", request.requester.mention()));
+            }
+
+            match until {
+                Some(until) => description.push_str(&format!(" • in {}", track_display::clock(until))),
+                None => description.push_str(" • later"),
+            }
+
+            description.push('\n');
+        }
+
+        until = until.zip(metadata.duration).map(|(until, duration)| until + duration);
+    }
+
+    if description.is_empty() {
+        description.push_str("Nothing");
+    }
+
+    let total = match until {
+        Some(total) => track_display::clock(total),
+        None => "unknown, some tracks are live".to_string(),
+    };
+
+    let response = Response::embed(|e|
+        // A page of entries can run past the 1024 characters a field holds, so it all goes in the description
+        e.title("**Queue**")
+            .description(format!("**• Now Playing**\n{}\n\n**• Up Next**\n{}", now_playing_text, description))
+            .color(Colour::GOLD)
+            .footer(|f| f.text(format!("Page {}/{} • {} track(s) waiting • Time left: {} • Loop: {}", page, pages, waiting.len(), total, loop_mode)))
+    );
+
+    if pages == 1 {
+        return Ok(response);
+    }
+
+    Ok(response.components(|c|
+        c.create_action_row(|r|
+            r.create_button(|b|
+                b.custom_id(format!("{}{}", QUEUE_BUTTON_PREFIX, page - 1))
+                    .emoji('◀')
+                    .label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page == 1)
+            )
+            .create_button(|b|
+                b.custom_id(format!("{}{}", QUEUE_BUTTON_PREFIX, page + 1))
+                    .emoji('▶')
+                    .label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(page == pages)
+            )
+        )
+    ))
+}
+
+pub async fn skip(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Not in a voice channel to play in")),
+    };
+
+    let (song, voice_channel) = {
+        let handler = handler_lock.lock().await;
+        (handler.queue().current(), handler.current_channel())
+    };
+
+    let song = match song {
+        Some(song) => song,
+        None => return Ok(Response::text("Not playing any music right now")),
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let threshold = database.guild_settings(invocation.guild_id)?.skip_vote_threshold;
+    let requested_by_invoker = track_requests::request(&song).await.is_some_and(|request| request.requester == invocation.user.id);
+
+    // Whoever asked for the track and DJs can always skip it
+    let bypass = requested_by_invoker || permissions::is_dj(ctx, invocation.guild_id, invocation.user.id).await;
+
+    if let (Some(threshold), Some(voice_channel), false) = (threshold, voice_channel, bypass) {
+        let listeners = listeners(ctx, invocation.guild_id, ChannelId(voice_channel.0));
+
+        if !listeners.contains(&invocation.user.id) {
+            return Ok(Response::text("You need to be listening in the voice channel to vote to skip"));
+        }
+
+        let votes = track_requests::vote_to_skip(&song, invocation.user.id, &listeners).await;
+        let needed = (listeners.len() * threshold as usize).div_ceil(100).max(1);
+
+        if votes < needed {
+            return Ok(Response::embed(|e|
+                action_embed(e, "Vote to Skip", format!("**{}/{}** votes to skip **{}**", votes, needed, track_display::title(song.metadata())), invocation.user)
+            ));
+        }
+    }
+
+    let _ = handler_lock.lock().await.queue().skip();
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Skipped Song", format!("Skipped **{}**.", track_display::title(song.metadata())), invocation.user)
+    ))
+}
+
+pub async fn stop(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Not in a voice channel to play in")),
+    };
+
+    // Otherwise queue looping would put each stopped track straight back
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+
+    if let Some(session) = sessions.lock().unwrap().get_mut(&invocation.guild_id) {
+        session.loop_mode = LoopMode::Off;
+    }
+
+    handler_lock.lock().await.queue().stop();
+
+    Ok(Response::text("Skipped song and cleared queue"))
+}
+
+/// Removes the track at `index`, where 1 is the next track up
+pub async fn remove(ctx: &Context, invocation: &Invocation<'_>, index: usize) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let handler = handler_lock.lock().await;
+    let queue = handler.queue();
+
+    if queue.is_empty() {
+        return Ok(Response::text("Nothing in the queue"));
+    }
+
+    // The current song is skipped rather than removed
+    if !is_waiting(index, queue.len()) {
+        return Ok(not_waiting(index, queue.len()));
+    }
+
+    let song = match queue.dequeue(index) {
+        Some(song) => song,
+        None => return Ok(not_waiting(index, queue.len())),
+    };
+
+    drop(handler);
+    track_requests::discard(&song).await;
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Removed Song from Queue", format!("Removed **{}** from the queue ({} in queue)", track_display::title(song.metadata()), num_prefix(index)), invocation.user)
+    ))
+}
+
+/// Sets the loop mode to `off`, `track` or `queue`, or toggles looping the current track when `mode` is empty
+pub async fn loop_track(ctx: &Context, invocation: &Invocation<'_>, mode: &str) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let current = match handler_lock.lock().await.queue().current() {
+        Some(current) => current,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let mode = match mode.trim() {
+        "" if loop_mode(ctx, invocation.guild_id).await == LoopMode::Off => LoopMode::Track,
+        "" => LoopMode::Off,
+        mode => match mode.parse::<LoopMode>() {
+            Ok(mode) => mode,
+            Err(_) => return Ok(Response::text("The loop mode must be `off`, `track` or `queue`")),
+        },
+    };
+
+    // Songbird loops a single track by itself. In queue mode the track has to end, so it can go to the back.
+    if mode == LoopMode::Track {
+        current.enable_loop()?;
+    } else {
+        current.disable_loop()?;
+    }
+
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+
+    if let Some(session) = sessions.lock().unwrap().get_mut(&invocation.guild_id) {
+        session.loop_mode = mode;
+    }
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        e.description(format!("Loop set to `{}`", mode))
+            .color(Colour::GOLD)
+    ))
+}
+
+/// The guild's loop mode, `off` if it has no music session
+pub async fn loop_mode(ctx: &Context, guild_id: GuildId) -> LoopMode {
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+    let mode = sessions.lock().unwrap().get(&guild_id).map(|session| session.loop_mode).unwrap_or_default();
+
+    mode
+}
+
+/// Sets the volume of every track in the queue, and of those queued later, to `percent`
+pub async fn volume(ctx: &Context, invocation: &Invocation<'_>, percent: u16) -> CommandResult<Response> {
+    if percent > audio::MAX_VOLUME {
+        return Ok(Response::text(format!("The volume must be a percentage from `0` to `{}`", audio::MAX_VOLUME)));
+    }
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_volume(invocation.guild_id, Some(percent))?;
+
+    if let Some(handler_lock) = call(ctx, invocation.guild_id).await {
+        let queue = handler_lock.lock().await.queue().current_queue();
+        let gain = audio::settings(ctx, invocation.guild_id).await.gain();
+
+        for track in queue {
+            let _ = track.set_volume(gain);
+        }
+
+        player::refresh(ctx, invocation.guild_id).await;
+    }
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Volume Changed", format!("Set the volume to `{}%`", percent), invocation.user)
+    ))
+}
+
+/// Shuffles the tracks waiting after the current one
+pub async fn shuffle(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let shuffled = handler_lock.lock().await.queue().modify_queue(|queue| {
+        if queue.len() < 3 {
+            return 0;
+        }
+
+        // The first track is the one playing, leave it where it is
+        queue.make_contiguous()[1..].shuffle(&mut rand::thread_rng());
+        queue.len() - 1
+    });
+
+    if shuffled == 0 {
+        return Ok(Response::text("There need to be at least 2 songs waiting to shuffle"));
+    }
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Shuffled Queue", format!("Shuffled {} songs", shuffled), invocation.user)
+    ))
+}
+
+/// Where `seek` jumps to in the current track
+#[derive(Debug, Clone, Copy)]
+pub enum SeekTarget {
+    To(Duration),
+    Forward(Duration),
+    Back(Duration),
+}
+
+pub async fn seek(ctx: &Context, invocation: &Invocation<'_>, target: SeekTarget) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let current_track = match handler_lock.lock().await.queue().current() {
+        Some(track) => track,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let metadata = current_track.metadata();
+    let title = track_display::title(metadata);
+
+    let duration = match metadata.duration {
+        Some(duration) if current_track.is_seekable() => duration,
+        _ => return Ok(Response::text(format!("Can't jump around in **{}**, it's a livestream", title))),
+    };
+
+    let elapsed = current_track.get_info().await?.position;
+
+    let position = match target {
+        SeekTarget::To(position) => position,
+        SeekTarget::Forward(offset) => elapsed + offset,
+        SeekTarget::Back(offset) => elapsed.saturating_sub(offset),
+    };
+
+    if position >= duration {
+        return Ok(Response::text(format!("**{}** is only `{}` long, use `skip` to move on", title, track_display::clock(duration))));
+    }
+
+    current_track.seek_time(position)?;
+    player::refresh(ctx, invocation.guild_id).await;
+
+    let action = match target {
+        SeekTarget::To(position) if position.is_zero() => "Replaying".to_string(),
+        SeekTarget::To(position) => format!("Jumped to `{}` in", track_display::clock(position)),
+        SeekTarget::Forward(offset) => format!("Forwarded {} in", duration_formatter(offset)),
+        SeekTarget::Back(offset) => format!("Rewound {} in", duration_formatter(offset)),
+    };
+
+    Ok(Response::text(format!("{} **{}**\n{}", action, title, track_display::progress_bar(position, Some(duration)))))
+}
+
+/// Turns `name` on, with `value` for `speed` and `pitch`, or off if it's already on.
+/// `off` turns every filter off.
+pub async fn filter(ctx: &Context, invocation: &Invocation<'_>, name: &str, value: Option<f64>) -> CommandResult<Response> {
+    let name = name.trim();
+
+    let filter = match name {
+        "off" => None,
+        name => match AudioFilter::parse(name, value) {
+            Ok(filter) => Some(filter),
+            Err(reason) => return Ok(Response::text(reason)),
+        },
+    };
+
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+
+    let (filters, description) = {
+        let mut sessions = sessions.lock().unwrap();
+
+        let session = match sessions.get_mut(&invocation.guild_id) {
+            Some(session) => session,
+            None => return Ok(Response::text("Nothing playing currently")),
+        };
+
+        let description = match filter {
+            None => {
+                session.filters.clear();
+                "Turned every filter off".to_string()
+            },
+            // Changing the speed or pitch replaces the old one, rather than turning it off
+            Some(filter) => match session.filters.iter().position(|active| active.name() == filter.name()) {
+                Some(i) if session.filters[i] == filter => {
+                    session.filters.remove(i);
+                    format!("Turned `{}` off", filter.name())
+                },
+                Some(i) => {
+                    session.filters[i] = filter;
+                    format!("Set `{}`", filter)
+                },
+                None => {
+                    session.filters.push(filter);
+                    format!("Turned `{}` on", filter)
+                },
+            },
+        };
+
+        (session.filters.clone(), description)
+    };
+
+    apply_effects(ctx, invocation.guild_id, true).await?;
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Filters", format!("{}\nNow on: {}", description, filter_list(&filters)), invocation.user)
+    ))
+}
+
+/// Lists the filters that are on, and the ones there are
+pub async fn filters(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let filters = audio::effects(ctx, invocation.guild_id).await.filters;
+    let available = AudioFilter::NAMES.map(|name| format!("`{}`", name)).join(", ");
+
+    Ok(Response::embed(|e|
+        e.title("Filters")
+            .field("• On", filter_list(&filters), false)
+            .field("• Available", available, false)
+            .color(Colour::GOLD)
+            .footer(|f| f.text(format!("Turn one on or off with filter <name>, speed and pitch take a number from {} to {}", audio::MIN_FACTOR, audio::MAX_FACTOR)))
+    ))
+}
+
+fn filter_list(filters: &[AudioFilter]) -> String {
+    if filters.is_empty() {
+        return "None".to_string();
+    }
+
+    filters.iter().map(|filter| format!("`{}`", filter)).collect::<Vec<_>>().join(", ")
+}
+
+/// Rebuilds the tracks in the queue that were made with other effects than the guild wants now,
+/// so that each one starts with them. With `include_current`, the playing track is rebuilt too and
+/// carries on from the same point in the song. songbird can't swap a track's source, so new tracks
+/// take the old ones' places in the queue.
+pub async fn apply_effects(ctx: &Context, guild_id: GuildId, include_current: bool) -> CommandResult {
+    let handler_lock = match call(ctx, guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(()),
+    };
+
+    let queue = handler_lock.lock().await.queue().current_queue();
+    let wanted = audio::effects(ctx, guild_id).await;
+    let looping = loop_mode(ctx, guild_id).await == LoopMode::Track;
+
+    let mut replacements = Vec::new();
+
+    for (index, old) in queue.iter().enumerate() {
+        if index == 0 && !include_current {
+            continue;
+        }
+
+        let had = audio::track_effects(old).await;
+
+        if had == wanted {
+            continue;
+        }
+
+        let target = match audio::track_target(old).await {
+            Some(target) => target,
+            None => {
+                tracing::warn!("Could not apply effects to a track in guild {}, its source wasn't kept", guild_id);
+                continue;
+            },
+        };
+
+        let info = old.get_info().await?;
+        let input = audio::rebuilt_source(old, target.clone(), &wanted).await?;
+        let (mut track, handle) = tracks::create_player(input);
+        track.set_volume(info.volume);
+
+        if index == 0 && looping {
+            track.set_loops(LoopState::Infinite)?;
+        }
+
+        audio::set_track_source(&handle, target, wanted.clone()).await;
+
+        if let Some(request) = track_requests::request(old).await {
+            track_requests::set_request(&handle, request).await;
+        }
+
+        // Both positions are in the track's own time, which the speed filters stretch
+        let position = if index == 0 { info.position.mul_f64(had.tempo() / wanted.tempo()) } else { Duration::ZERO };
+
+        replacements.push((old.clone(), track, handle, position));
+    }
+
+    let mut replaced = Vec::new();
+    let mut unused = Vec::new();
+
+    // Only the queue is touched while the call is locked
+    {
+        let mut handler = handler_lock.lock().await;
+
+        for (old, track, handle, position) in replacements {
+            handler.enqueue(track);
+
+            let found = handler.queue().modify_queue(|queue| {
+                let replacement = queue.pop_back()?;
+
+                match queue.iter().position(|queued| queued.uuid() == old.uuid()) {
+                    // Straight after the current track, which is about to stop
+                    Some(0) => queue.insert(1, replacement),
+                    Some(index) => {
+                        queue.remove(index);
+                        queue.insert(index, replacement);
+                    },
+                    None => return None,
+                }
+
+                Some(())
+            });
+
+            match found {
+                Some(()) => replaced.push((old, handle, position)),
+                // The old track left the queue in the meantime
+                None => unused.push(handle),
+            }
+        }
+    }
+
+    for (old, handle, position) in replaced {
+        if !position.is_zero() {
+            handle.seek_time(position)?;
+        }
+
+        track_requests::discard(&old).await;
+    }
+
+    for handle in unused {
+        let _ = handle.stop();
+    }
+
+    Ok(())
+}
+
+/// Moves the track at `from` to `to`, both positions as `queue` numbers them
+pub async fn move_track(ctx: &Context, invocation: &Invocation<'_>, from: usize, to: usize) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let moved = handler_lock.lock().await.queue().modify_queue(|queue| {
+        for position in [from, to] {
+            if !is_waiting(position, queue.len()) {
+                return Err(not_waiting(position, queue.len()));
+            }
+        }
+
+        let track = queue.remove(from).unwrap();
+        let title = track_display::title(track.metadata()).to_string();
+        queue.insert(to, track);
+
+        Ok(title)
+    });
+
+    let title = match moved {
+        Ok(title) => title,
+        Err(response) => return Ok(response),
+    };
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Moved Song", format!("Moved **{}** from {} to {} in the queue", title, num_prefix(from), num_prefix(to)), invocation.user)
+    ))
+}
+
+/// Swaps the tracks at `first` and `second`, both positions as `queue` numbers them
+pub async fn swap(ctx: &Context, invocation: &Invocation<'_>, first: usize, second: usize) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let swapped = handler_lock.lock().await.queue().modify_queue(|queue| {
+        for position in [first, second] {
+            if !is_waiting(position, queue.len()) {
+                return Err(not_waiting(position, queue.len()));
+            }
+        }
+
+        queue.swap(first, second);
+
+        Ok((
+            track_display::title(queue[first].metadata()).to_string(),
+            track_display::title(queue[second].metadata()).to_string(),
+        ))
+    });
+
+    let (now_first, now_second) = match swapped {
+        Ok(titles) => titles,
+        Err(response) => return Ok(response),
+    };
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Swapped Songs", format!("**{}** is now {} and **{}** is now {} in the queue", now_first, num_prefix(first), now_second, num_prefix(second)), invocation.user)
+    ))
+}
+
+/// Removes every track waiting after the current one
+pub async fn clear(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let cleared = handler_lock.lock().await.queue().modify_queue(|queue| {
+        if queue.len() < 2 {
+            return Vec::new();
+        }
+
+        queue.drain(1..).collect::<Vec<_>>()
+    });
+
+    if cleared.is_empty() {
+        return Ok(Response::text("Nothing waiting in the queue"));
+    }
+
+    for track in &cleared {
+        track_requests::discard(track).await;
+    }
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Cleared Queue", format!("Removed {} songs from the queue", cleared.len()), invocation.user)
+    ))
+}
+
+/// Skips the current track and every one before `position`, as `queue` numbers them.
+/// With the whole queue looping, the current and passed over tracks go to the back instead of being dropped.
+pub async fn skip_to(ctx: &Context, invocation: &Invocation<'_>, position: usize) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let looping = loop_mode(ctx, invocation.guild_id).await == LoopMode::Queue;
+
+    let passed_over = handler_lock.lock().await.queue().modify_queue(|queue| {
+        if !is_waiting(position, queue.len()) {
+            return Err(not_waiting(position, queue.len()));
+        }
+
+        let passed_over = queue.drain(1..position).collect::<Vec<_>>();
+        let title = track_display::title(queue[1].metadata()).to_string();
+
+        Ok((queue[0].handle(), title, passed_over))
+    });
+
+    let (current, title, passed_over) = match passed_over {
+        Ok(passed_over) => passed_over,
+        Err(response) => return Ok(response),
+    };
+
+    if looping {
+        // The loop carries on in the same order: the current track goes to the back first, then the ones passed over.
+        // Requeueing it here rather than when it ends keeps it in front of them.
+        requeue(ctx, invocation.guild_id, &current).await;
+        handler_lock.lock().await.queue().modify_queue(|queue| queue.extend(passed_over));
+        handler_lock.lock().await.queue().skip()?;
+    } else {
+        handler_lock.lock().await.queue().skip()?;
+
+        for track in &passed_over {
+            track_requests::discard(track).await;
+        }
+    }
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Skipped Ahead", format!("Skipped to **{}**", title), invocation.user)
+    ))
+}
+
+/// Puts a fresh copy of `track` at the end of the queue, requester and all.
+/// Only once, so a track requeued before it ends isn't requeued again when it does.
+pub async fn requeue(ctx: &Context, guild_id: GuildId, track: &TrackHandle) {
+    if !track_requests::mark_requeued(track).await {
+        return;
+    }
+
+    let target = match audio::track_target(track).await {
+        Some(target) => target,
+        None => {
+            tracing::warn!("Could not requeue a track in guild {}, its source wasn't kept", guild_id);
+            return;
+        },
+    };
+
+    let audio = audio::settings(ctx, guild_id).await;
+    let effects = audio::effects(ctx, guild_id).await;
+
+    // Uploads play from the saved file again, and everything else keeps the metadata it had
+    let input = match audio::rebuilt_source(track, target.clone(), &effects).await {
+        Ok(input) => input,
+        Err(why) => {
+            println!("Could not requeue track in guild {}: {:?}", guild_id, why);
+            return;
+        },
+    };
+
+    let handler_lock = match call(ctx, guild_id).await {
+        Some(handler) => handler,
+        None => return,
+    };
+
+    let requeued = handler_lock.lock().await.enqueue_source(input);
+    let _ = requeued.set_volume(audio.gain());
+    audio::set_track_source(&requeued, target, effects).await;
+
+    if let Some(request) = track_requests::request(track).await {
+        track_requests::set_request(&requeued, request).await;
+    }
+}
+
+/// Removes tracks that are already further up the queue, matching them by URL, or by title for those without one
+pub async fn remove_duplicates(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult<Response> {
+    let handler_lock = match call(ctx, invocation.guild_id).await {
+        Some(handler) => handler,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    let removed = handler_lock.lock().await.queue().modify_queue(|queue| {
+        let mut seen = HashSet::new();
+        let mut kept = VecDeque::new();
+        let mut removed = Vec::new();
+
+        // The current track is first, so it's always kept
+        for track in queue.drain(..) {
+            let metadata = track.metadata();
+            let key = metadata.source_url.clone().or_else(|| metadata.title.clone());
+
+            if key.is_some_and(|key| !seen.insert(key)) {
+                removed.push(track);
+            } else {
+                kept.push_back(track);
+            }
+        }
+
+        *queue = kept;
+        removed
+    });
+
+    if removed.is_empty() {
+        return Ok(Response::text("There are no duplicate songs in the queue"));
+    }
+
+    for track in &removed {
+        track_requests::discard(track).await;
+    }
+
+    player::refresh(ctx, invocation.guild_id).await;
+
+    Ok(Response::embed(|e|
+        action_embed(e, "Removed Duplicates", format!("Removed {} duplicate songs from the queue", removed.len()), invocation.user)
+    ))
+}
+
+/// Whether `position`, as `queue` numbers tracks, is one of the tracks waiting in a queue of `queue_len`.
+/// The current track is at index 0, so positions are also indices.
+fn is_waiting(position: usize, queue_len: usize) -> bool {
+    position >= 1 && position < queue_len
+}
+
+fn not_waiting(position: usize, queue_len: usize) -> Response {
+    match queue_len.saturating_sub(1) {
+        0 => Response::text("Nothing waiting in the queue"),
+        waiting => Response::text(format!("There is no song at position {}, the queue goes from 1 to {}", position, waiting)),
+    }
+}
@@ -0,0 +1,564 @@
+pub mod actions;
+pub mod permissions;
+pub mod player;
+
+use std::time::{Duration, Instant};
+
+use serenity::{
+    async_trait,
+    builder::CreateEmbed,
+    client::{Context},
+    framework::{
+        standard::{
+            macros::{command, group},
+            Args,
+            CommandResult,
+        },
+    },
+    model::{
+        application::interaction::message_component::MessageComponentInteraction,
+        channel::Message,
+        prelude::{ChannelId, GuildId},
+    },
+    utils::Colour,
+};
+
+use songbird::{
+    Event,
+    EventContext,
+    EventHandler as VoiceEventHandler,
+    TrackEvent,
+};
+
+use crate::commands::slash::{self, InteractionRecord};
+use crate::database::connection::DatabaseContainer;
+use crate::utils::audio::{self, SourceTarget};
+use crate::utils::music_sessions::{LoopMode, MusicSession, MusicSessions};
+use crate::utils::response::Response;
+use crate::utils::{track_display, track_requests};
+use crate::utils::utilities::duration_parser;
+use self::actions::{Invocation, SeekTarget};
+use self::permissions::DJ_CHECK;
+
+
+#[group]
+#[checks(Dj)]
+#[commands(join, leave, pause, resume, play, stop, queue, skip, skipto, seek, forward, rewind, replay, remove, removedupes, move_command, swap, clear, shuffle, loop_command, volume, filter, filters)]
+struct Music;
+
+
+struct TrackEndNotifier {
+    chan_id: ChannelId,
+    context: Context,
+    guild: GuildId
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(&[(_state, track)]) = ctx {
+            if track_requests::is_discarded(track).await {
+                return None;
+            }
+
+            let mut ended = CreateEmbed::default();
+            ended.title("Queue has Ended")
+                .description(format!("Last song played: **{}**\nTo continue listening, play another song!", track_display::title(track.metadata())))
+                .color(Colour::GOLD);
+
+            let manager = songbird::get(&self.context).await.unwrap();
+            let handle = manager.get(self.guild);
+
+            if handle.is_none() {
+                player::end(&self.context, self.guild, self.chan_id, ended).await;
+
+                drop(handle);
+                self.context.idle().await;
+
+                return None;
+            }
+
+            let handle = handle.unwrap();
+            let loop_mode = actions::loop_mode(&self.context, self.guild).await;
+
+            if loop_mode == LoopMode::Queue {
+                actions::requeue(&self.context, self.guild, track).await;
+            }
+
+            let mut handler = handle.lock().await;
+
+            if handler.queue().is_empty() {
+                let _ = handler.leave().await;
+                drop(handler);
+
+                player::end(&self.context, self.guild, self.chan_id, ended).await;
+
+                return None;
+            }
+
+            // Songbird's loop belongs to a track, so it has to be passed on to the next one
+            if loop_mode == LoopMode::Track {
+                if let Some(current) = handler.queue().current() {
+                    let _ = current.enable_loop();
+                }
+            }
+
+            drop(handler);
+
+            // The queue has moved on to the next track, show it on the same player message
+            player::refresh(&self.context, self.guild).await;
+        }
+
+        None
+    }
+}
+
+/// Rejoins and refills the queues saved at the last shutdown for `guilds`.
+/// Each shard restores its own guilds, since only it can join their voice channels.
+pub async fn restore_queues(ctx: &Context, guilds: Vec<GuildId>) {
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+
+    for guild_id in guilds {
+        let queue = match database.take_saved_queue(guild_id) {
+            Ok(Some(queue)) => queue,
+            Ok(None) => continue,
+            Err(why) => {
+                println!("Could not read the saved queue for guild {}: {:?}", guild_id, why);
+                continue;
+            }
+        };
+
+        let audio = audio::settings(ctx, guild_id).await;
+        let effects = audio::effects(ctx, guild_id).await;
+        let mut sources = Vec::new();
+
+        for url in &queue.tracks {
+            let target = SourceTarget::Url(url.clone());
+
+            match audio::source(target.clone(), &effects).await {
+                Ok(source) => sources.push((target, source)),
+                Err(why) => println!("Could not restore {} in guild {}: {:?}", url, queue.guild_id, why),
+            }
+        }
+
+        if sources.is_empty() {
+            continue;
+        }
+
+        let (handle_lock, joined) = manager.join(queue.guild_id, queue.voice_channel).await;
+
+        if let Err(why) = joined {
+            println!("Could not rejoin voice in guild {}: {:?}", queue.guild_id, why);
+            continue;
+        }
+
+        sessions.lock().unwrap().insert(queue.guild_id, MusicSession::new(queue.text_channel));
+
+        let mut handle = handle_lock.lock().await;
+
+        handle.add_global_event(
+            Event::Track(TrackEvent::End),
+            TrackEndNotifier {
+                chan_id: queue.text_channel,
+                context: ctx.clone(),
+                guild: queue.guild_id
+            },
+        );
+
+        let restored = sources.len();
+
+        for (i, (target, source)) in sources.into_iter().enumerate() {
+            let track = handle.enqueue_source(source);
+            let _ = track.set_volume(audio.gain());
+            audio::set_track_source(&track, target, effects.clone()).await;
+
+            if i == 0 {
+                let _ = track.seek_time(queue.position);
+            }
+        }
+
+        drop(handle);
+
+        let _ = queue.text_channel.send_message(ctx, |m|
+            m.embed(|e|
+                e.title("Queue Restored")
+                    .description(format!("Back again, carrying on with {} track(s)", restored))
+                    .color(Colour::GOLD)
+            )
+        ).await;
+
+        player::refresh(ctx, queue.guild_id).await;
+    }
+}
+
+/// Turns the page of a queue listing in place
+pub async fn handle_queue_button(ctx: &Context, component: &MessageComponentInteraction) {
+    let started = Instant::now();
+    let page = component.data.custom_id.trim_start_matches(actions::QUEUE_BUTTON_PREFIX);
+
+    let result = match (Invocation::from_component(component), page.parse::<usize>()) {
+        (Some(invocation), Ok(page)) => actions::queue(ctx, &invocation, page).await,
+        _ => Ok(Response::text("That page doesn't exist any more")),
+    };
+
+    let response = slash::settle(ctx, InteractionRecord {
+        guild_id: component.guild_id,
+        channel_id: component.channel_id,
+        user_id: component.user.id,
+        command_name: "queue:page",
+        args: page,
+        latency: started.elapsed(),
+    }, result).await;
+
+    // A page that no longer exists gets its own reply, rather than wiping out the listing
+    let sent = if response.embed.is_some() {
+        response.update_message(ctx, component).await
+    } else {
+        response.reply_ephemeral(ctx, component).await
+    };
+
+    if let Err(why) = sent {
+        println!("Could not turn the queue page: {:?}", why);
+    }
+}
+
+
+#[command]
+#[description = "Joins the voice channel you are currently in"]
+#[usage = "!join"]
+#[only_in(guilds)]
+async fn join(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::join(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Leaves the voice channel you are currently in"]
+#[usage = "!leave"]
+#[only_in(guilds)]
+async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::leave(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Pauses the current song playing"]
+#[usage = "!pause"]
+#[only_in(guilds)]
+async fn pause(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::pause(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Plays a song, or enqueues it if a song is already playing. Playlist links queue the whole playlist."]
+#[usage = "!play <song>"]
+#[only_in(guilds)]
+async fn play(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = actions::play(ctx, &Invocation::from_message(msg), args.message(), msg.attachments.first()).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Shows the queue, 10 songs to a page, with when each one will start"]
+#[usage = "!queue [page]"]
+#[only_in(guilds)]
+async fn queue(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let page = match args.rest().trim() {
+        "" => 1,
+        page => match page.parse::<usize>() {
+            Ok(page) => page,
+            Err(_) => {
+                msg.channel_id.say(ctx, "Please enter a page number (e.g. `2`)").await?;
+                return Ok(());
+            },
+        },
+    };
+
+    let response = actions::queue(ctx, &Invocation::from_message(msg), page).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Skips the song currently playing, or votes to skip it if the server has vote-skip on"]
+#[usage = "!skip"]
+#[only_in(guilds)]
+async fn skip(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::skip(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Clears the queue and stops playing the current song. Also leaves the voice channel."]
+#[usage = "!stop"]
+#[only_in(guilds)]
+async fn stop(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::stop(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Resumes the current song after pausing"]
+#[usage = "!resume"]
+#[only_in(guilds)]
+async fn resume(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::resume(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Skips straight to a song in the queue, dropping the ones before it"]
+#[usage = "!skipto <position>"]
+#[only_in(guilds)]
+async fn skipto(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let position = match args.parse::<usize>() {
+        Ok(position) => position,
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please enter a position in the queue (e.g. `3`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::skip_to(ctx, &Invocation::from_message(msg), position).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Jumps to a time in the current song"]
+#[usage = "!seek <time> (e.g. \"1:23\" or \"1m23s\")"]
+#[only_in(guilds)]
+async fn seek(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let position = match duration_parser(args.rest()) {
+        Some(position) => position,
+        None => {
+            msg.channel_id.say(ctx, "Please enter a time (e.g. `1:23` or `1m23s`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::seek(ctx, &Invocation::from_message(msg), SeekTarget::To(position)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Skips forward in the current song"]
+#[usage = "!forward <seconds>"]
+#[only_in(guilds)]
+async fn forward(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let offset = match duration_parser(args.rest()) {
+        Some(offset) => offset,
+        None => {
+            msg.channel_id.say(ctx, "Please enter how far to skip forward (e.g. `30` or `1m30s`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::seek(ctx, &Invocation::from_message(msg), SeekTarget::Forward(offset)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Goes back in the current song"]
+#[usage = "!rewind <seconds>"]
+#[only_in(guilds)]
+async fn rewind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let offset = match duration_parser(args.rest()) {
+        Some(offset) => offset,
+        None => {
+            msg.channel_id.say(ctx, "Please enter how far to go back (e.g. `30` or `1m30s`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::seek(ctx, &Invocation::from_message(msg), SeekTarget::Back(offset)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Starts the current song again from the beginning"]
+#[usage = "!replay"]
+#[only_in(guilds)]
+async fn replay(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::seek(ctx, &Invocation::from_message(msg), SeekTarget::To(Duration::ZERO)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Removes a song in the queue"]
+#[usage = "!remove <position>"]
+#[only_in(guilds)]
+async fn remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let index = match args.parse::<usize>() {
+        Ok(index) => index,
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please enter a position in the queue (e.g. `1`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::remove(ctx, &Invocation::from_message(msg), index).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Removes songs that are in the queue more than once, keeping the first of each"]
+#[usage = "!removedupes"]
+#[only_in(guilds)]
+async fn removedupes(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::remove_duplicates(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command("move")]
+#[description = "Moves a song to another position in the queue"]
+#[usage = "!move <from> <to>"]
+#[only_in(guilds)]
+async fn move_command(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (from, to) = match (args.single::<usize>(), args.single::<usize>()) {
+        (Ok(from), Ok(to)) => (from, to),
+        _ => {
+            msg.channel_id.say(ctx, "Please enter the song's position and where to move it (e.g. `5 1`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::move_track(ctx, &Invocation::from_message(msg), from, to).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Swaps two songs in the queue"]
+#[usage = "!swap <position> <position>"]
+#[only_in(guilds)]
+async fn swap(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (first, second) = match (args.single::<usize>(), args.single::<usize>()) {
+        (Ok(first), Ok(second)) => (first, second),
+        _ => {
+            msg.channel_id.say(ctx, "Please enter the positions of the two songs (e.g. `2 4`)").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::swap(ctx, &Invocation::from_message(msg), first, second).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Removes every song waiting in the queue, but keeps the current one playing"]
+#[usage = "!clear"]
+#[only_in(guilds)]
+async fn clear(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::clear(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Shuffles the songs waiting in the queue"]
+#[usage = "!shuffle"]
+#[only_in(guilds)]
+async fn shuffle(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::shuffle(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command("loop")]
+#[description = "Loops the current track or the whole queue, or turns looping off. Without a mode, toggles looping the current track."]
+#[usage = "!loop [off/track/queue]"]
+#[only_in(guilds)]
+async fn loop_command(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let response = actions::loop_track(ctx, &Invocation::from_message(msg), args.message()).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Sets the volume for everything playing and queued, and for songs queued from now on"]
+#[usage = "!volume <0-200>"]
+#[only_in(guilds)]
+async fn volume(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let volume = match args.message().trim().trim_end_matches('%').parse::<u16>() {
+        Ok(volume) => volume,
+        Err(_) => {
+            msg.channel_id.say(ctx, "The volume must be a percentage from `0` to `200`").await?;
+            return Ok(());
+        },
+    };
+
+    let response = actions::volume(ctx, &Invocation::from_message(msg), volume).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Turns an audio filter on or off for everything playing: bassboost, nightcore, vaporwave, 8d, speed, pitch or karaoke. `off` turns them all off."]
+#[usage = "!filter <name> [value] (e.g. \"bassboost\" or \"speed 1.25\")"]
+#[only_in(guilds)]
+async fn filter(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = match args.single::<String>() {
+        Ok(name) => name,
+        Err(_) => {
+            msg.channel_id.say(ctx, "Please enter a filter (e.g. `bassboost` or `speed 1.25`), or `off`").await?;
+            return Ok(());
+        },
+    };
+
+    let value = args.single::<f64>().ok();
+
+    let response = actions::filter(ctx, &Invocation::from_message(msg), &name, value).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Shows the audio filters that are on, and the ones there are"]
+#[usage = "!filters"]
+#[only_in(guilds)]
+async fn filters(ctx: &Context, msg: &Message) -> CommandResult {
+    let response = actions::filters(ctx, &Invocation::from_message(msg)).await?;
+    response.send(ctx, msg.channel_id).await?;
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Who may run the music commands that affect everyone listening. Those need the server's DJ role,
+//! the Manage Channels permission, or for nobody else to be listening. The same rule covers the
+//! prefix commands (through the `Dj` check on the Music group), slash commands and player buttons.
+
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandOptions, Reason};
+use serenity::framework::standard::macros::check;
+use serenity::model::channel::Message;
+use serenity::model::Permissions;
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+
+use super::actions::{listeners, loop_mode};
+use crate::database::connection::DatabaseContainer;
+use crate::utils::music_sessions::LoopMode;
+use crate::utils::track_requests;
+
+
+/// Whether `user_id` has the guild's DJ role or Manage Channels
+pub async fn is_dj(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+    let member = match guild_id.member(ctx, user_id).await {
+        Ok(member) => member,
+        Err(_) => return false,
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    let dj_role = database.guild_settings_or_default(guild_id).dj_role;
+
+    if dj_role.is_some_and(|role_id| member.roles.contains(&role_id)) {
+        return true;
+    }
+
+    member.permissions(&ctx.cache).is_ok_and(|permissions| permissions.contains(Permissions::MANAGE_CHANNELS))
+}
+
+/// Passes for DJs, and for anyone when the bot isn't in voice or `user_id` is the only one listening
+pub async fn require_dj(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Result<(), String> {
+    let voice_channel = match songbird::get(ctx).await.and_then(|manager| manager.get(guild_id)) {
+        Some(call) => call.lock().await.current_channel(),
+        None => None,
+    };
+
+    let alone = match voice_channel {
+        Some(channel) => listeners(ctx, guild_id, ChannelId(channel.0)) == [user_id],
+        None => true,
+    };
+
+    if alone || is_dj(ctx, guild_id, user_id).await {
+        return Ok(());
+    }
+
+    Err("You need the DJ role or the `Manage Channels` permission to do that while others are listening".to_string())
+}
+
+/// Applies the rule to the music command called `command`, given its arguments as they'd be typed
+pub async fn check_command(ctx: &Context, guild_id: GuildId, user_id: UserId, command: &str, args: &str) -> Result<(), String> {
+    match command {
+        "stop" | "volume" | "clear" | "move" | "swap" | "skipto" | "removedupes" => require_dj(ctx, guild_id, user_id).await,
+        "seek" | "forward" | "rewind" | "replay" | "filter" => require_dj(ctx, guild_id, user_id).await,
+        // Looping the queue affects everyone, so turning it on or off in any way (a mode, a bare toggle, or the button) needs DJ
+        "loop" => {
+            let current = loop_mode(ctx, guild_id).await;
+
+            if current == LoopMode::Queue || args.trim().parse::<LoopMode>() == Ok(LoopMode::Queue) {
+                return require_dj(ctx, guild_id, user_id).await;
+            }
+
+            Ok(())
+        },
+        "remove" => {
+            if requested_at(ctx, guild_id, args).await == Some(user_id) {
+                return Ok(());
+            }
+
+            require_dj(ctx, guild_id, user_id).await
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Who requested the track at `index` in the queue, as given to `remove`
+async fn requested_at(ctx: &Context, guild_id: GuildId, index: &str) -> Option<UserId> {
+    let index = index.trim().parse::<usize>().ok()?;
+    let call = songbird::get(ctx).await?.get(guild_id)?;
+    let track = call.lock().await.queue().current_queue().get(index)?.clone();
+
+    Some(track_requests::request(&track).await?.requester)
+}
+
+
+#[check]
+#[name = "Dj"]
+async fn dj_check(ctx: &Context, msg: &Message, args: &mut Args, options: &CommandOptions) -> Result<(), Reason> {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return Ok(()),
+    };
+
+    check_command(ctx, guild_id, msg.author.id, options.names[0], args.rest()).await.map_err(Reason::User)
+}
@@ -0,0 +1,281 @@
+//! The live "Now playing" message each music session keeps, edited in place as tracks change,
+//! and the buttons on it. The buttons run the same actions as the music commands.
+
+use std::time::{Duration, Instant};
+
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::client::Context;
+use serenity::framework::standard::CommandResult;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::utils::Colour;
+
+use songbird::tracks::PlayMode;
+use songbird::input::Metadata;
+
+use super::actions::{self, Invocation};
+use super::permissions;
+use crate::commands::slash::{self, InteractionRecord};
+use crate::utils::audio;
+use crate::utils::music_sessions::{LoopMode, MusicSession, MusicSessions};
+use crate::utils::response::Response;
+use crate::utils::track_display::{self, TrackStyle};
+use crate::utils::track_requests::{self, TrackRequest};
+
+
+/// Custom IDs of the player buttons all start with this
+pub const BUTTON_PREFIX: &str = "player:";
+
+/// How much each volume button changes the volume by, in percent
+const VOLUME_STEP: u16 = 10;
+
+
+/// What the player shows, read from the call
+struct PlayerState {
+    metadata: Metadata,
+    /// How far into the track it was when the state was read
+    position: Duration,
+    paused: bool,
+    loop_mode: LoopMode,
+    /// In percent
+    volume: u16,
+    /// The title of the next track, if there is one
+    up_next: Option<String>,
+    /// Tracks waiting after the current one
+    waiting: usize,
+    request: Option<TrackRequest>,
+}
+
+/// `None` when nothing is playing in the guild
+async fn player_state(ctx: &Context, guild_id: GuildId) -> Option<PlayerState> {
+    let call = songbird::get(ctx).await?.get(guild_id)?;
+
+    let (current, queue) = {
+        let handler = call.lock().await;
+        (handler.queue().current()?, handler.queue().current_queue())
+    };
+
+    let info = current.get_info().await.ok()?;
+
+    Some(PlayerState {
+        metadata: current.metadata().clone(),
+        position: info.position,
+        paused: matches!(info.playing, PlayMode::Pause),
+        loop_mode: actions::loop_mode(ctx, guild_id).await,
+        volume: (info.volume * 100.0).round() as u16,
+        up_next: queue.get(1).map(|track| track_display::title(track.metadata()).to_string()),
+        waiting: queue.len().saturating_sub(1),
+        request: track_requests::request(&current).await,
+    })
+}
+
+fn player_embed(state: &PlayerState, style: TrackStyle) -> CreateEmbed {
+    let mut e = CreateEmbed::default();
+    track_display::track_embed(&mut e, &state.metadata, Some(state.position), style);
+
+    e.title(if state.paused { "**Paused**" } else { "**Now playing**" });
+
+    if state.paused {
+        e.color(Colour::GOLD);
+    }
+
+    let volume = format!("{}%", state.volume);
+    let looping = match state.loop_mode {
+        LoopMode::Off => "Off",
+        LoopMode::Track => "This track",
+        LoopMode::Queue => "Whole queue",
+    };
+
+    let up_next = match &state.up_next {
+        Some(title) if state.waiting > 1 => format!("{} (and {} more)", title, state.waiting - 1),
+        Some(title) => title.clone(),
+        None => "Nothing".to_string(),
+    };
+
+    let requested_by = state.request.as_ref().map_or("Unknown".to_string(), TrackRequest::describe);
+
+    match style {
+        TrackStyle::Compact => e.field("• Requested by", requested_by, false)
+            .footer(|f| f.text(format!("Volume {} • Loop {} • Up next: {}", volume, looping, up_next))),
+        TrackStyle::Verbose => e.field("• Requested by", requested_by, false)
+            .field("• Volume", volume, true)
+            .field("• Loop", looping, true)
+            .field("• Up Next", up_next, false),
+    };
+
+    e
+}
+
+fn player_buttons<'a>(c: &'a mut CreateComponents, state: &PlayerState) -> &'a mut CreateComponents {
+    c.create_action_row(|r|
+        r.create_button(|b|
+            b.custom_id(format!("{}toggle", BUTTON_PREFIX))
+                .emoji(if state.paused { '▶' } else { '⏸' })
+                .label(if state.paused { "Resume" } else { "Pause" })
+                .style(ButtonStyle::Primary)
+        )
+        .create_button(|b|
+            b.custom_id(format!("{}skip", BUTTON_PREFIX))
+                .emoji('⏭')
+                .label("Skip")
+                .style(ButtonStyle::Secondary)
+        )
+        .create_button(|b|
+            b.custom_id(format!("{}stop", BUTTON_PREFIX))
+                .emoji('⏹')
+                .label("Stop")
+                .style(ButtonStyle::Danger)
+        )
+        .create_button(|b|
+            b.custom_id(format!("{}loop", BUTTON_PREFIX))
+                .emoji(if state.loop_mode == LoopMode::Track { '🔂' } else { '🔁' })
+                .label(format!("Loop: {}", state.loop_mode))
+                .style(if state.loop_mode == LoopMode::Off { ButtonStyle::Secondary } else { ButtonStyle::Success })
+        )
+    )
+    .create_action_row(|r|
+        r.create_button(|b|
+            b.custom_id(format!("{}shuffle", BUTTON_PREFIX))
+                .emoji('🔀')
+                .label("Shuffle")
+                .style(ButtonStyle::Secondary)
+                .disabled(state.waiting < 2)
+        )
+        .create_button(|b|
+            b.custom_id(format!("{}volume_down", BUTTON_PREFIX))
+                .emoji('🔉')
+                .style(ButtonStyle::Secondary)
+                .disabled(state.volume == 0)
+        )
+        .create_button(|b|
+            b.custom_id(format!("{}volume_up", BUTTON_PREFIX))
+                .emoji('🔊')
+                .style(ButtonStyle::Secondary)
+                .disabled(state.volume >= audio::MAX_VOLUME)
+        )
+    )
+}
+
+
+/// Shows what's playing on the guild's player message, sending one if it doesn't have one yet.
+/// Must not be called while holding the guild's call lock.
+pub async fn refresh(ctx: &Context, guild_id: GuildId) {
+    let state = match player_state(ctx, guild_id).await {
+        Some(state) => state,
+        None => return,
+    };
+
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+
+    let session = match sessions.lock().unwrap().get(&guild_id).cloned() {
+        Some(session) => session,
+        None => return,
+    };
+
+    let embed = player_embed(&state, track_display::style(ctx, guild_id).await);
+
+    if let Some(message_id) = session.player_message {
+        let edited = session.text_channel.edit_message(ctx, message_id, |m|
+            m.set_embed(embed.clone()).components(|c| player_buttons(c, &state))
+        ).await;
+
+        // Otherwise it was probably deleted, so send a new one
+        if edited.is_ok() {
+            return;
+        }
+    }
+
+    let sent = session.text_channel.send_message(ctx, |m|
+        m.set_embed(embed).components(|c| player_buttons(c, &state))
+    ).await;
+
+    match sent {
+        Ok(message) => {
+            if let Some(session) = sessions.lock().unwrap().get_mut(&guild_id) {
+                session.player_message = Some(message.id);
+            }
+        },
+        Err(why) => println!("Could not send the player for guild {}: {:?}", guild_id, why),
+    }
+}
+
+/// Closes the guild's player with `embed` once the music has stopped, or sends it to `fallback` if there's no session
+pub async fn end(ctx: &Context, guild_id: GuildId, fallback: ChannelId, embed: CreateEmbed) {
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+
+    // Take the message out of the session first, so the next track gets a new player
+    let session = sessions.lock().unwrap()
+        .get_mut(&guild_id)
+        .map(|session| MusicSession { player_message: session.player_message.take(), ..session.clone() })
+        .unwrap_or_else(|| MusicSession::new(fallback));
+
+    session.close_player(&ctx.http, embed).await;
+}
+
+
+/// Runs the pressed button and answers only the presser, the player itself is updated by the action
+pub async fn handle_button(ctx: &Context, component: &MessageComponentInteraction) {
+    let started = Instant::now();
+    let button = component.data.custom_id.trim_start_matches(BUTTON_PREFIX);
+
+    let result = match Invocation::from_component(component) {
+        Some(invocation) => press(ctx, &invocation, button).await,
+        None => Ok(Response::text("This button can only be used in a server")),
+    };
+
+    let command_name = format!("{}{}", BUTTON_PREFIX, button);
+
+    let response = slash::settle(ctx, InteractionRecord {
+        guild_id: component.guild_id,
+        channel_id: component.channel_id,
+        user_id: component.user.id,
+        command_name: &command_name,
+        args: "",
+        latency: started.elapsed(),
+    }, result).await;
+
+    if let Err(why) = response.reply_ephemeral(ctx, component).await {
+        println!("Could not respond to {}: {:?}", command_name, why);
+    }
+}
+
+async fn press(ctx: &Context, invocation: &Invocation<'_>, button: &str) -> CommandResult<Response> {
+    // The loop button steps through off, track and queue
+    let next_loop_mode = match actions::loop_mode(ctx, invocation.guild_id).await {
+        LoopMode::Off => LoopMode::Track,
+        LoopMode::Track => LoopMode::Queue,
+        LoopMode::Queue => LoopMode::Off,
+    };
+
+    let (command, args) = match button {
+        "volume_down" | "volume_up" => ("volume", ""),
+        "loop" => ("loop", next_loop_mode.as_str()),
+        other => (other, ""),
+    };
+
+    if let Err(reason) = permissions::check_command(ctx, invocation.guild_id, invocation.user.id, command, args).await {
+        return Ok(Response::text(reason));
+    }
+
+    match button {
+        "skip" => return actions::skip(ctx, invocation).await,
+        "stop" => return actions::stop(ctx, invocation).await,
+        "loop" => return actions::loop_track(ctx, invocation, next_loop_mode.as_str()).await,
+        "shuffle" => return actions::shuffle(ctx, invocation).await,
+        _ => {},
+    }
+
+    let state = match player_state(ctx, invocation.guild_id).await {
+        Some(state) => state,
+        None => return Ok(Response::text("Nothing playing currently")),
+    };
+
+    match button {
+        "toggle" if state.paused => actions::resume(ctx, invocation).await,
+        "toggle" => actions::pause(ctx, invocation).await,
+        "volume_down" => actions::volume(ctx, invocation, state.volume.saturating_sub(VOLUME_STEP)).await,
+        "volume_up" => actions::volume(ctx, invocation, (state.volume + VOLUME_STEP).min(audio::MAX_VOLUME)).await,
+        _ => Ok(Response::text("That button doesn't do anything any more")),
+    }
+}
@@ -0,0 +1,320 @@
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::{group, command};
+use serenity::model::channel::Message;
+use serenity::model::prelude::{ChannelId, RoleId};
+use serenity::prelude::*;
+
+use crate::commands::music::{actions, player};
+use crate::database::connection::DatabaseContainer;
+use crate::database::guild_settings;
+use crate::utils::audio;
+use crate::utils::track_display::TrackStyle;
+use crate::utils::utilities::rand_color;
+
+
+#[group]
+#[commands(settings)]
+struct Settings;
+
+#[command]
+#[description = "Shows this server's settings"]
+#[usage = "!settings [setting] [value]"]
+#[sub_commands(prefix, modlog, trackstyle, voteskip, djrole, normalize)]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn settings(ctx: &Context, msg: &Message) -> CommandResult {
+    let prefix = guild_settings::prefix(ctx, msg.guild_id).await;
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let settings = database.guild_settings(msg.guild_id.unwrap())?;
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.title("**Server Settings**")
+                .description(format!("Change a setting with `{}settings <setting> <value>`", prefix))
+                .field("• Prefix", format!("`{}`", prefix), true)
+                .field("• Mod-log", settings.modlog_channel.map_or("Off".to_string(), |id| format!("<#{}>", id.0)), true)
+                .field("• Track style", settings.track_style.unwrap_or_default(), true)
+                .field("• Vote-skip", settings.skip_vote_threshold.map_or("Off".to_string(), |threshold| format!("{}% of listeners", threshold)), true)
+                .field("• DJ role", settings.dj_role.map_or("None".to_string(), |id| format!("<@&{}>", id.0)), true)
+                .field("• Volume", format!("{}%", settings.volume.unwrap_or(audio::DEFAULT_VOLUME)), true)
+                .field("• Normalization", if settings.normalize { "On" } else { "Off" }, true)
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Requested by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Sets the command prefix for this server. Use `reset` to go back to the default."]
+#[usage = "!settings prefix <prefix/reset>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn prefix(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let new_prefix = args.rest().trim();
+
+    if new_prefix.is_empty() {
+        let prefix = guild_settings::prefix(ctx, msg.guild_id).await;
+        msg.channel_id.say(ctx, format!("The prefix is `{}`", prefix)).await?;
+
+        return Ok(());
+    }
+
+    if new_prefix.chars().any(char::is_whitespace) || new_prefix.chars().count() > 5 {
+        msg.channel_id.say(ctx, "The prefix must be 1-5 characters with no spaces").await?;
+
+        return Ok(());
+    }
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    if new_prefix.eq_ignore_ascii_case("reset") {
+        database.set_prefix(guild_id, None)?;
+    } else {
+        database.set_prefix(guild_id, Some(new_prefix))?;
+    }
+
+    let prefix = guild_settings::prefix(ctx, msg.guild_id).await;
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.description(format!("Prefix set to `{}`", prefix))
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Invoked by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Sets the channel moderation cases are posted to. Use `off` to stop posting them."]
+#[usage = "!settings modlog <channel/off>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(1)]
+async fn modlog(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let arg = args.single::<String>()?;
+
+    let channel_id = if arg.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        match arg.parse::<ChannelId>() {
+            Ok(channel_id) if msg.guild(&ctx.cache).unwrap().channels.contains_key(&channel_id) => Some(channel_id),
+            _ => {
+                msg.channel_id.say(ctx, "Please mention a channel in this server, or use `off`").await?;
+                return Ok(());
+            },
+        }
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_modlog_channel(guild_id, channel_id)?;
+
+    let description = match channel_id {
+        Some(channel_id) => format!("Moderation cases will be posted in <#{}>", channel_id.0),
+        None => "Moderation cases will no longer be posted".to_string(),
+    };
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.description(description)
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Invoked by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Sets how much the music player shows about the current track: `compact` or `verbose`. Use `reset` to go back to the default."]
+#[usage = "!settings trackstyle <compact/verbose/reset>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(1)]
+async fn trackstyle(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let arg = args.single::<String>()?;
+
+    let style = if arg.eq_ignore_ascii_case("reset") {
+        None
+    } else {
+        match arg.parse::<TrackStyle>() {
+            Ok(style) => Some(style),
+            Err(_) => {
+                msg.channel_id.say(ctx, "The track style must be `compact`, `verbose` or `reset`").await?;
+                return Ok(());
+            },
+        }
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_track_style(guild_id, style)?;
+    player::refresh(ctx, guild_id).await;
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.description(format!("Track style set to `{}`", style.unwrap_or_default()))
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Invoked by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Makes skipping a song take votes from a percentage of the people listening. Whoever asked for the song and DJs skip without a vote. Use `off` to let anyone skip straight away."]
+#[usage = "!settings voteskip <1-100/off>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(1)]
+async fn voteskip(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let arg = args.single::<String>()?;
+
+    let threshold = if arg.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        match arg.trim_end_matches('%').parse::<u8>() {
+            Ok(threshold) if (1..=100).contains(&threshold) => Some(threshold),
+            _ => {
+                msg.channel_id.say(ctx, "The threshold must be a percentage from `1` to `100`, or `off`").await?;
+                return Ok(());
+            },
+        }
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_skip_vote_threshold(guild_id, threshold)?;
+
+    let description = match threshold {
+        Some(threshold) => format!("Skipping a song now takes votes from {}% of listeners", threshold),
+        None => "Anyone can skip a song straight away".to_string(),
+    };
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.description(description)
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Invoked by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Sets the role that can stop the music, change the volume and remove other people's songs. Use `off` to leave it to members with Manage Channels."]
+#[usage = "!settings djrole <role/off>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(1)]
+async fn djrole(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let arg = args.rest().trim().trim_matches('"');
+
+    // Like `!role`, the role can be a mention, an ID or a name
+    let role_id = if arg.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        let guild = msg.guild(&ctx.cache).unwrap();
+
+        let role = match arg.parse::<RoleId>() {
+            Ok(role_id) => guild.roles.get(&role_id),
+            Err(_) => guild.role_by_name(arg),
+        };
+
+        match role {
+            Some(role) => Some(role.id),
+            None => {
+                msg.channel_id.say(ctx, format!("There is no role called `{}`", arg)).await?;
+                return Ok(());
+            },
+        }
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_dj_role(guild_id, role_id)?;
+
+    let description = match role_id {
+        Some(role_id) => format!("<@&{}> is now the DJ role", role_id.0),
+        None => "There is no DJ role any more, only members with Manage Channels count as DJs".to_string(),
+    };
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.description(description)
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Invoked by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Evens out how loud songs are, so quiet ones aren't drowned out by loud ones. Applies from the next song."]
+#[usage = "!settings normalize <on/off>"]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[min_args(1)]
+async fn normalize(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let normalize = match args.single::<String>()?.to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            msg.channel_id.say(ctx, "Normalization must be `on` or `off`").await?;
+            return Ok(());
+        },
+    };
+
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    database.set_normalize(guild_id, normalize)?;
+
+    // The song playing now keeps going as it is, the rest of the queue is rebuilt before it starts
+    actions::apply_effects(ctx, guild_id, false).await?;
+
+    let description = if normalize {
+        "From the next song, songs will be normalized to the same loudness"
+    } else {
+        "From the next song, songs will play at their own loudness"
+    };
+
+    msg.channel_id.send_message(ctx, |m|
+        m.embed(|e|
+            e.description(description)
+                .color(rand_color())
+                .footer(|f|
+                    f.text(format!("Invoked by {}", msg.author.name))
+                        .icon_url(msg.author.face())
+                )
+        )
+    ).await?;
+
+    Ok(())
+}
@@ -0,0 +1,449 @@
+//! Slash command versions of the music, utility and help commands. Each one runs the same
+//! logic as its prefix command, only reading its options from the interaction instead of `Args`.
+//! Also routes the other interactions, such as player buttons, to where they're handled.
+
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde_json::Value;
+use serenity::builder::{CreateApplicationCommand, CreateApplicationCommandOption, CreateApplicationCommands};
+use serenity::framework::standard::CommandResult;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::Interaction;
+use serenity::model::application::interaction::application_command::{ApplicationCommandInteraction, CommandDataOption};
+use serenity::model::application::interaction::autocomplete::AutocompleteInteraction;
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::prelude::*;
+
+use crate::commands::{help, utility, GROUPS};
+use crate::commands::music::actions::{self, Invocation, SeekTarget};
+use crate::commands::music::{self, permissions, player};
+use crate::utils::audio::{self, AudioFilter};
+use crate::utils::audit_log::{AuditLogContainer, AuditRecord};
+use crate::utils::errors::{self, BotError, ErrorOrigin};
+use crate::utils::metrics::MetricsContainer;
+use crate::utils::response::Response;
+use crate::utils::utilities::duration_parser;
+use crate::utils::ytdlp;
+
+
+/// How long `/play` suggestions may take before we answer with none. Discord gives up after 3 seconds.
+const AUTOCOMPLETE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+/// Searches shorter than this aren't worth running yt-dlp for
+const AUTOCOMPLETE_MIN_LENGTH: usize = 3;
+
+const AUTOCOMPLETE_RESULTS: usize = 5;
+
+/// Discord's limit on command descriptions and choice names and values
+const MAX_LENGTH: usize = 100;
+
+
+/// Every slash command, as registered with `!sync`
+pub fn create_commands(commands: &mut CreateApplicationCommands) -> &mut CreateApplicationCommands {
+    commands
+        .create_application_command(|c|
+            describe(c, "help")
+                .create_option(|o|
+                    o.name("command")
+                        .description("The command or category to show help for")
+                        .kind(CommandOptionType::String)
+                )
+        )
+        .create_application_command(|c| describe(c, "ping"))
+        .create_application_command(|c| music(c, "join"))
+        .create_application_command(|c| music(c, "leave"))
+        .create_application_command(|c| music(c, "pause"))
+        .create_application_command(|c| music(c, "resume"))
+        .create_application_command(|c|
+            music(c, "play")
+                .create_option(|o|
+                    o.name("query")
+                        .description("A link, or what to search YouTube for")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .set_autocomplete(true)
+                )
+        )
+        .create_application_command(|c|
+            music(c, "queue")
+                .create_option(|o|
+                    o.name("page")
+                        .description("The page to start on")
+                        .kind(CommandOptionType::Integer)
+                        .min_int_value(1)
+                )
+        )
+        .create_application_command(|c| music(c, "skip"))
+        .create_application_command(|c|
+            music(c, "skipto")
+                .create_option(|o| position(o, "position", "Position in the queue of the song to skip to"))
+        )
+        .create_application_command(|c|
+            music(c, "seek")
+                .create_option(|o| time(o, "time", "Where to jump to, e.g. 1:23 or 1m23s"))
+        )
+        .create_application_command(|c|
+            music(c, "forward")
+                .create_option(|o| time(o, "by", "How far to skip forward, e.g. 30 or 1m30s"))
+        )
+        .create_application_command(|c|
+            music(c, "rewind")
+                .create_option(|o| time(o, "by", "How far to go back, e.g. 30 or 1m30s"))
+        )
+        .create_application_command(|c| music(c, "replay"))
+        .create_application_command(|c| music(c, "stop"))
+        .create_application_command(|c|
+            music(c, "remove")
+                .create_option(|o| position(o, "position", "Position in the queue, 1 being the next song up"))
+        )
+        .create_application_command(|c| music(c, "removedupes"))
+        .create_application_command(|c|
+            music(c, "move")
+                .create_option(|o| position(o, "from", "Position in the queue of the song to move"))
+                .create_option(|o| position(o, "to", "Position in the queue to move it to"))
+        )
+        .create_application_command(|c|
+            music(c, "swap")
+                .create_option(|o| position(o, "first", "Position in the queue of one song"))
+                .create_option(|o| position(o, "second", "Position in the queue of the other"))
+        )
+        .create_application_command(|c| music(c, "clear"))
+        .create_application_command(|c| music(c, "shuffle"))
+        .create_application_command(|c|
+            music(c, "loop")
+                .create_option(|o|
+                    o.name("mode")
+                        .description("Leave out to toggle looping the current track")
+                        .kind(CommandOptionType::String)
+                        .add_string_choice("off", "off")
+                        .add_string_choice("track", "track")
+                        .add_string_choice("queue", "queue")
+                )
+        )
+        .create_application_command(|c|
+            music(c, "filter")
+                .create_option(|o| {
+                    o.name("name")
+                        .description("The filter to turn on or off, or off to turn them all off")
+                        .kind(CommandOptionType::String)
+                        .required(true);
+
+                    for name in AudioFilter::NAMES.iter().chain(&["off"]) {
+                        o.add_string_choice(name, name);
+                    }
+
+                    o
+                })
+                .create_option(|o|
+                    o.name("value")
+                        .description("How much faster or higher, for speed and pitch")
+                        .kind(CommandOptionType::Number)
+                        .min_number_value(audio::MIN_FACTOR)
+                        .max_number_value(audio::MAX_FACTOR)
+                )
+        )
+        .create_application_command(|c| music(c, "filters"))
+        .create_application_command(|c|
+            music(c, "volume")
+                .create_option(|o|
+                    o.name("volume")
+                        .description("The new volume")
+                        .kind(CommandOptionType::Integer)
+                        .min_int_value(0)
+                        .max_int_value(audio::MAX_VOLUME)
+                        .required(true)
+                )
+        )
+}
+
+/// Names the command and reuses the description of the prefix command with the same name
+fn describe<'a>(c: &'a mut CreateApplicationCommand, name: &str) -> &'a mut CreateApplicationCommand {
+    let description = GROUPS.iter()
+        .flat_map(|group| group.options.commands)
+        .find(|command| command.options.names.contains(&name))
+        .and_then(|command| command.options.desc)
+        .unwrap_or(name);
+
+    c.name(name).description(truncate(description, MAX_LENGTH))
+}
+
+/// Music commands need a voice channel, so they're hidden in DMs
+fn music<'a>(c: &'a mut CreateApplicationCommand, name: &str) -> &'a mut CreateApplicationCommand {
+    describe(c, name).dm_permission(false)
+}
+
+/// A required position in the queue, numbered as `/queue` shows it
+fn position<'a>(o: &'a mut CreateApplicationCommandOption, name: &str, description: &str) -> &'a mut CreateApplicationCommandOption {
+    o.name(name)
+        .description(description)
+        .kind(CommandOptionType::Integer)
+        .min_int_value(1)
+        .required(true)
+}
+
+/// A required time, in any form `duration_parser` reads
+fn time<'a>(o: &'a mut CreateApplicationCommandOption, name: &str, description: &str) -> &'a mut CreateApplicationCommandOption {
+    o.name(name)
+        .description(description)
+        .kind(CommandOptionType::String)
+        .required(true)
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+
+pub async fn handle_interaction(ctx: &Context, interaction: Interaction) {
+    match interaction {
+        Interaction::ApplicationCommand(command) => run(ctx, &command).await,
+        Interaction::MessageComponent(component) if component.data.custom_id.starts_with(player::BUTTON_PREFIX) =>
+            player::handle_button(ctx, &component).await,
+        Interaction::MessageComponent(component) if component.data.custom_id.starts_with(actions::QUEUE_BUTTON_PREFIX) =>
+            music::handle_queue_button(ctx, &component).await,
+        Interaction::Autocomplete(autocomplete) => {
+            if let Err(why) = suggest(ctx, &autocomplete).await {
+                println!("Could not answer autocomplete for /{}: {:?}", autocomplete.data.name, why);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Runs a slash command and reports it the same way the prefix command hooks do
+async fn run(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let started = Instant::now();
+
+    // Loading a track can take longer than the 3 seconds Discord waits for a first response
+    if let Err(why) = command.defer(ctx).await {
+        println!("Could not defer /{}: {:?}", command.data.name, why);
+        return;
+    }
+
+    let deferred = started.elapsed();
+    let command_name = format!("/{}", command.data.name);
+    let args = describe_options(&command.data.options);
+
+    let result = dispatch(ctx, command, deferred).await;
+
+    let response = settle(ctx, InteractionRecord {
+        guild_id: command.guild_id,
+        channel_id: command.channel_id,
+        user_id: command.user.id,
+        command_name: &command_name,
+        args: &args,
+        latency: started.elapsed(),
+    }, result).await;
+
+    if let Err(why) = response.edit_original(ctx, command).await {
+        println!("Could not respond to {}: {:?}", command_name, why);
+    }
+}
+
+/// An interaction that was run, for metrics, the audit log and error reports
+pub struct InteractionRecord<'a> {
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    /// `/name` for slash commands
+    pub command_name: &'a str,
+    pub args: &'a str,
+    pub latency: Duration,
+}
+
+/// Counts and logs a finished interaction the same way the prefix command hooks do.
+/// Returns the reply to send, which for errors is what the user is told about it.
+pub async fn settle(ctx: &Context, record: InteractionRecord<'_>, result: CommandResult<Response>) -> Response {
+    let (metrics, audit_log) = {
+        let data = ctx.data.read().await;
+        (data.get::<MetricsContainer>().unwrap().clone(), data.get::<AuditLogContainer>().unwrap().clone())
+    };
+
+    metrics.record(record.command_name, result.is_ok(), Some(record.latency));
+
+    let (response, outcome, error, error_id) = match result {
+        Ok(response) => (response, "ok", None, None),
+        Err(why) => {
+            let why = BotError::from(why);
+            let origin = ErrorOrigin {
+                created_at: Utc::now().timestamp(),
+                guild_id: record.guild_id,
+                channel_id: record.channel_id,
+                user_id: record.user_id,
+                content: format!("{} {}", record.command_name, record.args).trim_end().to_string(),
+            };
+
+            let id = errors::record(ctx, origin, record.command_name, &why).await;
+            (Response::text(errors::user_reply(&why, &id)), "error", Some(why.to_string()), Some(id))
+        },
+    };
+
+    let audit = AuditRecord {
+        timestamp: Utc::now().to_rfc3339(),
+        guild_id: record.guild_id.map(|id| id.0),
+        channel_id: record.channel_id.0,
+        user_id: record.user_id.0,
+        command: record.command_name,
+        args: record.args,
+        latency_ms: Some(record.latency.as_millis()),
+        outcome,
+        error,
+        error_id,
+    };
+
+    if let Err(why) = audit_log.write(&audit) {
+        println!("Could not write to the audit log: {:?}", why);
+    }
+
+    response
+}
+
+async fn dispatch(ctx: &Context, command: &ApplicationCommandInteraction, deferred: Duration) -> CommandResult<Response> {
+    let options = &command.data.options;
+
+    match command.data.name.as_str() {
+        "help" => return Ok(help::slash_help(ctx, &command.user, command.guild_id, string_option(options, "command")).await),
+        "ping" => return Ok(Response::text(utility::pong(deferred, utility::gateway_latency(ctx).await))),
+        _ => {},
+    }
+
+    let invocation = match Invocation::from_interaction(command) {
+        Some(invocation) => invocation,
+        None => return Ok(Response::text("This command can only be used in a server")),
+    };
+
+    let args = options.iter()
+        .filter_map(|option| option.value.as_ref())
+        .map(|value| value.as_str().map_or(value.to_string(), str::to_string))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if let Err(reason) = permissions::check_command(ctx, invocation.guild_id, invocation.user.id, &command.data.name, &args).await {
+        return Ok(Response::text(reason));
+    }
+
+    match command.data.name.as_str() {
+        "join" => actions::join(ctx, &invocation).await,
+        "leave" => actions::leave(ctx, &invocation).await,
+        "pause" => actions::pause(ctx, &invocation).await,
+        "resume" => actions::resume(ctx, &invocation).await,
+        "play" => actions::play(ctx, &invocation, string_option(options, "query").unwrap_or_default(), None).await,
+        "queue" => {
+            let page = option(options, "page").and_then(Value::as_u64).unwrap_or(1);
+            actions::queue(ctx, &invocation, page as usize).await
+        },
+        "skip" => actions::skip(ctx, &invocation).await,
+        "stop" => actions::stop(ctx, &invocation).await,
+        "skipto" => actions::skip_to(ctx, &invocation, position_option(options, "position")).await,
+        "seek" | "forward" | "rewind" => {
+            let time = match string_option(options, "time").or(string_option(options, "by")).and_then(duration_parser) {
+                Some(time) => time,
+                None => return Ok(Response::text("Please enter a time (e.g. `1:23` or `1m23s`)")),
+            };
+
+            let target = match command.data.name.as_str() {
+                "seek" => SeekTarget::To(time),
+                "forward" => SeekTarget::Forward(time),
+                _ => SeekTarget::Back(time),
+            };
+
+            actions::seek(ctx, &invocation, target).await
+        },
+        "replay" => actions::seek(ctx, &invocation, SeekTarget::To(Duration::ZERO)).await,
+        "remove" => actions::remove(ctx, &invocation, position_option(options, "position")).await,
+        "removedupes" => actions::remove_duplicates(ctx, &invocation).await,
+        "move" => actions::move_track(ctx, &invocation, position_option(options, "from"), position_option(options, "to")).await,
+        "swap" => actions::swap(ctx, &invocation, position_option(options, "first"), position_option(options, "second")).await,
+        "clear" => actions::clear(ctx, &invocation).await,
+        "shuffle" => actions::shuffle(ctx, &invocation).await,
+        "loop" => actions::loop_track(ctx, &invocation, string_option(options, "mode").unwrap_or_default()).await,
+        "volume" => {
+            let volume = option(options, "volume").and_then(Value::as_u64).unwrap_or(audio::DEFAULT_VOLUME as u64);
+            actions::volume(ctx, &invocation, volume.min(u16::MAX as u64) as u16).await
+        },
+        "filter" => actions::filter(ctx, &invocation, string_option(options, "name").unwrap_or_default(), option(options, "value").and_then(Value::as_f64)).await,
+        "filters" => actions::filters(ctx, &invocation).await,
+        // Left over from an older registration, `!sync` again to remove it
+        other => Ok(Response::text(format!("`/{}` isn't a command any more", other))),
+    }
+}
+
+fn option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a Value> {
+    options.iter().find(|option| option.name == name).and_then(|option| option.value.as_ref())
+}
+
+fn position_option(options: &[CommandDataOption], name: &str) -> usize {
+    option(options, name).and_then(Value::as_u64).unwrap_or(1) as usize
+}
+
+fn string_option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    option(options, name).and_then(Value::as_str)
+}
+
+/// The options as they'd be typed, e.g. `query: never gonna give you up`, for the audit log and error reports
+fn describe_options(options: &[CommandDataOption]) -> String {
+    options.iter()
+        .map(|option| match &option.value {
+            Some(Value::String(value)) => format!("{}: {}", option.name, value),
+            Some(value) => format!("{}: {}", option.name, value),
+            None => option.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+
+/// Suggests YouTube results for `/play` as the query is typed
+async fn suggest(ctx: &Context, autocomplete: &AutocompleteInteraction) -> serenity::Result<()> {
+    let query = autocomplete.data.options.iter()
+        .find(|option| option.focused)
+        .and_then(|option| option.value.as_ref())
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .trim();
+
+    let searchable = autocomplete.data.name == "play"
+        && query.chars().count() >= AUTOCOMPLETE_MIN_LENGTH
+        && !query.starts_with("http");
+
+    let results = if searchable {
+        match tokio::time::timeout(AUTOCOMPLETE_TIMEOUT, ytdlp::search(query, AUTOCOMPLETE_RESULTS)).await {
+            Ok(Ok(results)) => results,
+            Ok(Err(why)) => {
+                println!("Could not search for autocomplete: {:?}", why);
+                Vec::new()
+            },
+            Err(_) => Vec::new(),
+        }
+    } else {
+        Vec::new()
+    };
+
+    autocomplete.create_autocomplete_response(ctx, |r| {
+        for entry in &results {
+            let (title, url) = match (&entry.title, entry.playable_url()) {
+                (Some(title), Some(url)) if url.len() <= MAX_LENGTH => (title, url),
+                _ => continue,
+            };
+
+            let name = match entry.duration {
+                Some(duration) => {
+                    let length = format!(" ({}:{:02})", duration as u64 / 60, duration as u64 % 60);
+                    format!("{}{}", truncate(title, MAX_LENGTH - length.chars().count()), length)
+                },
+                None => truncate(title, MAX_LENGTH),
+            };
+
+            r.add_string_choice(name, url);
+        }
+
+        r
+    }).await
+}
@@ -1,25 +1,47 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+use serenity::client::bridge::gateway::ShardId;
 use serenity::framework::standard::{CommandResult};
 use serenity::framework::standard::macros::{group, command};
 use serenity::model::channel::Message;
 use serenity::prelude::*;
 
+use crate::utils::shutdown::ShardManagerContainer;
+
 
 #[group]
 #[commands(ping)]
 struct Utilities;
 
 #[command]
-#[description = "Check the latency of the bot"]
+#[description = "Check the latency of the bot, both to Discord's API and over this shard's gateway connection"]
 #[usage = "!ping"]
 async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
     let before = Instant::now();
     let mut m = msg.reply(ctx, "Pong!").await?;
-    let after = Instant::now();
-    let delay = (after - before).as_millis();
+    let rest = before.elapsed();
 
-    m.edit(ctx, |c| c.content(format!("Pong! `{}` ms", delay))).await.expect("Error in editing message in ping command");
+    let content = pong(rest, gateway_latency(ctx).await);
+    m.edit(ctx, |c| c.content(content)).await.expect("Error in editing message in ping command");
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Heartbeat latency of the shard `ctx` belongs to, only known once its first heartbeat has been acknowledged
+pub async fn gateway_latency(ctx: &Context) -> Option<Duration> {
+    let shard_manager = ctx.data.read().await.get::<ShardManagerContainer>().unwrap().clone();
+    let manager = shard_manager.lock().await;
+    let runners = manager.runners.lock().await;
+
+    runners.get(&ShardId(ctx.shard_id)).and_then(|runner| runner.latency)
+}
+
+/// The reply to `!ping` and `/ping`, given how long a REST round trip took
+pub fn pong(rest: Duration, gateway: Option<Duration>) -> String {
+    let gateway = match gateway {
+        Some(latency) => format!("`{}` ms", latency.as_millis()),
+        None => "not measured yet".to_string(),
+    };
+
+    format!("Pong! REST `{}` ms • Gateway {}", rest.as_millis(), gateway)
+}
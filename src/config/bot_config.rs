@@ -0,0 +1,818 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serenity::model::gateway::Activity;
+use serenity::model::user::OnlineStatus;
+use serenity::model::prelude::{ChannelId, UserId};
+use serenity::prelude::*;
+
+
+/// Shares the loaded configuration through the client's data map
+pub struct ConfigContainer;
+
+impl TypeMapKey for ConfigContainer {
+    type Value = Arc<RwLock<Config>>;
+}
+
+/// Name of the profile that uses the top level of `config.json` untouched
+pub const DEFAULT_PROFILE: &str = "prod";
+
+
+/// Validated bot configuration, built from `config.json`, the selected profile and the environment
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub path: PathBuf,
+    pub profile: String,
+    pub token: String,
+    pub owners: Vec<UserId>,
+    pub mention_id: Option<UserId>,
+    pub prefix: String,
+    pub intents: GatewayIntents,
+    pub shards: ShardsConfig,
+    pub activity: ActivityConfig,
+    pub status: StatusKind,
+    pub audit_log: AuditLogConfig,
+    pub database: PathBuf,
+    /// Where to serve Prometheus metrics, if anywhere
+    pub metrics: Option<SocketAddr>,
+    /// Where full details of failed commands are posted, for owners to see
+    pub error_channel: Option<ChannelId>,
+    /// The most tracks one playlist link adds to the queue
+    pub playlist_limit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActivityConfig {
+    pub kind: ActivityKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Playing,
+    Listening,
+    #[default]
+    Watching,
+    Competing,
+}
+
+/// Which shards this process runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShardsConfig {
+    /// As many as Discord recommends, all in this process
+    #[default]
+    Auto,
+    /// A fixed number of shards, all in this process
+    Fixed(u64),
+    /// Shards `first` to `last` (inclusive) out of `total`, with the rest run by other processes
+    Range { first: u64, last: u64, total: u64 },
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusKind {
+    Online,
+    #[default]
+    Idle,
+    Dnd,
+    Invisible,
+}
+
+/// Where command audit records go, and when their files rotate
+#[derive(Debug, Clone)]
+pub struct AuditLogConfig {
+    pub dir: PathBuf,
+    /// A file is rotated once it reaches this size, as well as at the start of each day (UTC)
+    pub max_size_mb: u64,
+    /// The oldest files are deleted once there are more than this many
+    pub max_files: usize,
+}
+
+/// Shape of `config.json` as written on disk, before validation
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawConfig {
+    token: Option<String>,
+    test_token: Option<String>,
+    owners: Option<Vec<UserId>>,
+    mention_id: Option<UserId>,
+    prefix: Option<String>,
+    intents: Option<IntentsConfig>,
+    shards: Option<RawShards>,
+    activity: Option<RawActivity>,
+    status: Option<StatusKind>,
+    audit_log: Option<RawAuditLog>,
+    database: Option<PathBuf>,
+    metrics: Option<SocketAddr>,
+    error_channel: Option<ChannelId>,
+    playlist_limit: Option<usize>,
+    #[serde(default)]
+    profiles: HashMap<String, RawProfile>,
+}
+
+/// Values a named profile, or the environment, may replace
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawProfile {
+    token: Option<String>,
+    owners: Option<Vec<UserId>>,
+    mention_id: Option<UserId>,
+    prefix: Option<String>,
+    intents: Option<IntentsConfig>,
+    shards: Option<RawShards>,
+    activity: Option<RawActivity>,
+    status: Option<StatusKind>,
+    audit_log: Option<RawAuditLog>,
+    database: Option<PathBuf>,
+    metrics: Option<SocketAddr>,
+    error_channel: Option<ChannelId>,
+    playlist_limit: Option<usize>,
+}
+
+/// `activity` as written, where each field left out keeps the value set before it
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawActivity {
+    kind: Option<ActivityKind>,
+    text: Option<String>,
+}
+
+/// `audit-log` as written, where each field left out keeps the value set before it
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawAuditLog {
+    dir: Option<PathBuf>,
+    max_size_mb: Option<u64>,
+    max_files: Option<usize>,
+}
+
+/// `"auto"`, a shard count, or `{ "first": 0, "last": 3, "total": 8 }`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawShards {
+    Preset(String),
+    Count(u64),
+    Range(RawShardRange),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RawShardRange {
+    first: u64,
+    last: u64,
+    total: u64,
+}
+
+/// Either a preset name (`"all"`, `"non-privileged"`) or a list of intent names
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum IntentsConfig {
+    Preset(String),
+    List(Vec<String>),
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    Env(&'static str, String),
+    UnknownProfile(String, Vec<String>),
+    Invalid(String),
+}
+
+
+impl Default for ActivityConfig {
+    fn default() -> Self {
+        ActivityConfig { kind: ActivityKind::Watching, text: "OSD".to_string() }
+    }
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        AuditLogConfig { dir: PathBuf::from("logs"), max_size_mb: 10, max_files: 30 }
+    }
+}
+
+impl ActivityConfig {
+    pub fn to_activity(&self) -> Activity {
+        match self.kind {
+            ActivityKind::Playing => Activity::playing(&self.text),
+            ActivityKind::Listening => Activity::listening(&self.text),
+            ActivityKind::Watching => Activity::watching(&self.text),
+            ActivityKind::Competing => Activity::competing(&self.text),
+        }
+    }
+}
+
+impl FromStr for ActivityKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "playing" => Ok(ActivityKind::Playing),
+            "listening" => Ok(ActivityKind::Listening),
+            "watching" => Ok(ActivityKind::Watching),
+            "competing" => Ok(ActivityKind::Competing),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for StatusKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "online" => Ok(StatusKind::Online),
+            "idle" => Ok(StatusKind::Idle),
+            "dnd" => Ok(StatusKind::Dnd),
+            "invisible" => Ok(StatusKind::Invisible),
+            _ => Err(()),
+        }
+    }
+}
+
+impl StatusKind {
+    pub fn to_online_status(self) -> OnlineStatus {
+        match self {
+            StatusKind::Online => OnlineStatus::Online,
+            StatusKind::Idle => OnlineStatus::Idle,
+            StatusKind::Dnd => OnlineStatus::DoNotDisturb,
+            StatusKind::Invisible => OnlineStatus::Invisible,
+        }
+    }
+}
+
+impl IntentsConfig {
+    fn resolve(&self) -> Result<GatewayIntents, ConfigError> {
+        match self {
+            IntentsConfig::Preset(preset) => match preset.as_str() {
+                "all" => Ok(GatewayIntents::all()),
+                "non-privileged" => Ok(GatewayIntents::non_privileged()),
+                other => intent_from_name(other),
+            },
+            IntentsConfig::List(names) => names.iter()
+                .try_fold(GatewayIntents::empty(), |acc, name| Ok(acc | intent_from_name(name)?)),
+        }
+    }
+}
+
+impl RawShards {
+    fn resolve(&self) -> Result<ShardsConfig, ConfigError> {
+        let shards = match self {
+            RawShards::Preset(preset) if preset == "auto" => ShardsConfig::Auto,
+            RawShards::Preset(other) => return Err(ConfigError::Invalid(format!("`shards`: expected \"auto\", a number or a range, got {:?}", other))),
+            RawShards::Count(0) => return Err(ConfigError::Invalid("`shards` must be at least 1".to_string())),
+            RawShards::Count(count) => ShardsConfig::Fixed(*count),
+            RawShards::Range(RawShardRange { first, last, total }) => {
+                if first > last || last >= total {
+                    return Err(ConfigError::Invalid(format!(
+                        "`shards`: the range {}-{} does not fit in {} shard(s), shards are numbered from 0", first, last, total
+                    )));
+                }
+
+                ShardsConfig::Range { first: *first, last: *last, total: *total }
+            },
+        };
+
+        Ok(shards)
+    }
+
+    /// Parses `auto`, a count such as `4`, or a range such as `0-3/8`
+    fn from_env(value: &str) -> Option<RawShards> {
+        let value = value.trim();
+
+        if let Ok(count) = value.parse() {
+            return Some(RawShards::Count(count));
+        }
+
+        match value.split_once('/') {
+            Some((range, total)) => {
+                let (first, last) = range.split_once('-')?;
+
+                Some(RawShards::Range(RawShardRange {
+                    first: first.trim().parse().ok()?,
+                    last: last.trim().parse().ok()?,
+                    total: total.trim().parse().ok()?,
+                }))
+            },
+            None => Some(RawShards::Preset(value.to_lowercase())),
+        }
+    }
+}
+
+fn intent_from_name(name: &str) -> Result<GatewayIntents, ConfigError> {
+    let intent = match name.trim().to_uppercase().replace('-', "_").as_str() {
+        "GUILDS" => GatewayIntents::GUILDS,
+        "GUILD_MEMBERS" => GatewayIntents::GUILD_MEMBERS,
+        "GUILD_BANS" => GatewayIntents::GUILD_BANS,
+        "GUILD_EMOJIS_AND_STICKERS" => GatewayIntents::GUILD_EMOJIS_AND_STICKERS,
+        "GUILD_INTEGRATIONS" => GatewayIntents::GUILD_INTEGRATIONS,
+        "GUILD_WEBHOOKS" => GatewayIntents::GUILD_WEBHOOKS,
+        "GUILD_INVITES" => GatewayIntents::GUILD_INVITES,
+        "GUILD_VOICE_STATES" => GatewayIntents::GUILD_VOICE_STATES,
+        "GUILD_PRESENCES" => GatewayIntents::GUILD_PRESENCES,
+        "GUILD_MESSAGES" => GatewayIntents::GUILD_MESSAGES,
+        "GUILD_MESSAGE_REACTIONS" => GatewayIntents::GUILD_MESSAGE_REACTIONS,
+        "GUILD_MESSAGE_TYPING" => GatewayIntents::GUILD_MESSAGE_TYPING,
+        "DIRECT_MESSAGES" => GatewayIntents::DIRECT_MESSAGES,
+        "DIRECT_MESSAGE_REACTIONS" => GatewayIntents::DIRECT_MESSAGE_REACTIONS,
+        "DIRECT_MESSAGE_TYPING" => GatewayIntents::DIRECT_MESSAGE_TYPING,
+        "MESSAGE_CONTENT" => GatewayIntents::MESSAGE_CONTENT,
+        "GUILD_SCHEDULED_EVENTS" => GatewayIntents::GUILD_SCHEDULED_EVENTS,
+        "AUTO_MODERATION_CONFIGURATION" => GatewayIntents::AUTO_MODERATION_CONFIGURATION,
+        "AUTO_MODERATION_EXECUTION" => GatewayIntents::AUTO_MODERATION_EXECUTION,
+        _ => return Err(ConfigError::Invalid(format!("`intents`: unknown intent `{}`", name))),
+    };
+
+    Ok(intent)
+}
+
+
+impl RawConfig {
+    /// Replaces every value that is set in `profile`
+    fn overlay(&mut self, profile: RawProfile) {
+        if profile.token.is_some() { self.token = profile.token; }
+        if profile.owners.is_some() { self.owners = profile.owners; }
+        if profile.mention_id.is_some() { self.mention_id = profile.mention_id; }
+        if profile.prefix.is_some() { self.prefix = profile.prefix; }
+        if profile.intents.is_some() { self.intents = profile.intents; }
+        if profile.shards.is_some() { self.shards = profile.shards; }
+        if let Some(activity) = profile.activity { self.activity.get_or_insert_with(Default::default).overlay(activity); }
+        if profile.status.is_some() { self.status = profile.status; }
+        if let Some(audit_log) = profile.audit_log { self.audit_log.get_or_insert_with(Default::default).overlay(audit_log); }
+        if profile.database.is_some() { self.database = profile.database; }
+        if profile.metrics.is_some() { self.metrics = profile.metrics; }
+        if profile.error_channel.is_some() { self.error_channel = profile.error_channel; }
+        if profile.playlist_limit.is_some() { self.playlist_limit = profile.playlist_limit; }
+    }
+
+    /// Picks the named profile out of `profiles`. `test` falls back to the older `test-token` key.
+    fn select_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        if let Some(profile) = self.profiles.remove(name) {
+            self.overlay(profile);
+            return Ok(());
+        }
+
+        match name {
+            DEFAULT_PROFILE => Ok(()),
+            "test" if self.test_token.is_some() => {
+                self.token = self.test_token.take();
+                Ok(())
+            },
+            _ => {
+                let mut known: Vec<String> = self.profiles.keys().cloned().collect();
+                known.push(DEFAULT_PROFILE.to_string());
+                if self.test_token.is_some() { known.push("test".to_string()); }
+                known.sort();
+
+                Err(ConfigError::UnknownProfile(name.to_string(), known))
+            },
+        }
+    }
+}
+
+impl RawActivity {
+    fn overlay(&mut self, other: RawActivity) {
+        if other.kind.is_some() { self.kind = other.kind; }
+        if other.text.is_some() { self.text = other.text; }
+    }
+
+    fn resolve(self) -> ActivityConfig {
+        let default = ActivityConfig::default();
+
+        ActivityConfig { kind: self.kind.unwrap_or(default.kind), text: self.text.unwrap_or(default.text) }
+    }
+}
+
+impl RawAuditLog {
+    fn overlay(&mut self, other: RawAuditLog) {
+        if other.dir.is_some() { self.dir = other.dir; }
+        if other.max_size_mb.is_some() { self.max_size_mb = other.max_size_mb; }
+        if other.max_files.is_some() { self.max_files = other.max_files; }
+    }
+
+    fn resolve(self) -> AuditLogConfig {
+        let default = AuditLogConfig::default();
+
+        AuditLogConfig {
+            dir: self.dir.unwrap_or(default.dir),
+            max_size_mb: self.max_size_mb.unwrap_or(default.max_size_mb),
+            max_files: self.max_files.unwrap_or(default.max_files),
+        }
+    }
+}
+
+impl RawProfile {
+    /// Reads overrides from `SKYBOT_*` environment variables
+    fn from_env() -> Result<RawProfile, ConfigError> {
+        let owners = match env_var("SKYBOT_OWNERS") {
+            Some(value) => Some(value.split(',')
+                .map(|id| id.trim().parse::<u64>().map(UserId::from))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| ConfigError::Env("SKYBOT_OWNERS", "expected a comma separated list of user IDs".to_string()))?),
+            None => None,
+        };
+
+        let mention_id = match env_var("SKYBOT_MENTION_ID") {
+            Some(value) => Some(value.trim().parse::<u64>().map(UserId::from)
+                .map_err(|_| ConfigError::Env("SKYBOT_MENTION_ID", "expected a user ID".to_string()))?),
+            None => None,
+        };
+
+        let intents = env_var("SKYBOT_INTENTS").map(|value| {
+            if value.contains(',') {
+                IntentsConfig::List(value.split(',').map(str::to_string).collect())
+            } else {
+                IntentsConfig::Preset(value)
+            }
+        });
+
+        let metrics = match env_var("SKYBOT_METRICS") {
+            Some(value) => Some(value.trim().parse::<SocketAddr>()
+                .map_err(|_| ConfigError::Env("SKYBOT_METRICS", "expected an address such as 127.0.0.1:9100".to_string()))?),
+            None => None,
+        };
+
+        let error_channel = match env_var("SKYBOT_ERROR_CHANNEL") {
+            Some(value) => Some(value.trim().parse::<u64>().map(ChannelId::from)
+                .map_err(|_| ConfigError::Env("SKYBOT_ERROR_CHANNEL", "expected a channel ID".to_string()))?),
+            None => None,
+        };
+
+        let shards = match env_var("SKYBOT_SHARDS") {
+            Some(value) => Some(RawShards::from_env(&value)
+                .ok_or_else(|| ConfigError::Env("SKYBOT_SHARDS", "expected `auto`, a shard count, or a range such as `0-3/8`".to_string()))?),
+            None => None,
+        };
+
+        let activity = env_var("SKYBOT_ACTIVITY").map(|text| RawActivity { text: Some(text), ..Default::default() });
+
+        let status = match env_var("SKYBOT_STATUS") {
+            Some(value) => Some(value.trim().parse::<StatusKind>()
+                .map_err(|_| ConfigError::Env("SKYBOT_STATUS", "expected one of online, idle, dnd or invisible".to_string()))?),
+            None => None,
+        };
+
+        let playlist_limit = match env_var("SKYBOT_PLAYLIST_LIMIT") {
+            Some(value) => Some(value.trim().parse::<usize>()
+                .map_err(|_| ConfigError::Env("SKYBOT_PLAYLIST_LIMIT", "expected a number of tracks".to_string()))?),
+            None => None,
+        };
+
+        Ok(RawProfile {
+            token: env_var("SKYBOT_TOKEN"),
+            owners,
+            mention_id,
+            prefix: env_var("SKYBOT_PREFIX"),
+            intents,
+            shards,
+            activity,
+            status,
+            audit_log: env_var("SKYBOT_AUDIT_DIR").map(|dir| RawAuditLog { dir: Some(PathBuf::from(dir)), ..Default::default() }),
+            database: env_var("SKYBOT_DATABASE").map(PathBuf::from),
+            metrics,
+            error_channel,
+            playlist_limit,
+        })
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+
+impl Config {
+    /// Reads the configuration file at `path`, applies `profile` and any `SKYBOT_*` environment overrides, then validates the result
+    pub fn load(path: impl AsRef<Path>, profile: &str) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+
+        let data = fs::read_to_string(path)
+            .map_err(|why| ConfigError::Io(path.to_path_buf(), why))?;
+
+        let mut raw: RawConfig = serde_json::from_str(&data)
+            .map_err(|why| ConfigError::Parse(path.to_path_buf(), why))?;
+
+        raw.select_profile(profile)?;
+        raw.overlay(RawProfile::from_env()?);
+
+        Config::from_raw(raw, path, profile)
+    }
+
+    fn from_raw(raw: RawConfig, path: &Path, profile: &str) -> Result<Config, ConfigError> {
+        let token = match raw.token {
+            Some(token) if !token.trim().is_empty() => token,
+            Some(_) => return Err(ConfigError::Invalid("`token` must not be empty".to_string())),
+            None => return Err(ConfigError::Invalid(format!(
+                "no `token` for profile `{}`, set it in {} or through SKYBOT_TOKEN", profile, path.display()
+            ))),
+        };
+
+        let prefix = raw.prefix.unwrap_or_else(|| "!".to_string());
+
+        if prefix.is_empty() || prefix.chars().any(char::is_whitespace) {
+            return Err(ConfigError::Invalid(format!("`prefix` must be non-empty and contain no whitespace, got {:?}", prefix)));
+        }
+
+        let activity = raw.activity.unwrap_or_default().resolve();
+
+        if activity.text.trim().is_empty() {
+            return Err(ConfigError::Invalid("`activity.text` must not be empty".to_string()));
+        }
+
+        let audit_log = raw.audit_log.unwrap_or_default().resolve();
+
+        if audit_log.max_size_mb == 0 || audit_log.max_files == 0 {
+            return Err(ConfigError::Invalid("`audit-log.max-size-mb` and `audit-log.max-files` must be at least 1".to_string()));
+        }
+
+        let intents = match raw.intents {
+            Some(intents) => intents.resolve()?,
+            None => GatewayIntents::all(),
+        };
+
+        if intents.is_empty() {
+            return Err(ConfigError::Invalid("`intents` must enable at least one intent".to_string()));
+        }
+
+        let shards = match raw.shards {
+            Some(shards) => shards.resolve()?,
+            None => ShardsConfig::Auto,
+        };
+
+        let playlist_limit = raw.playlist_limit.unwrap_or(100);
+
+        if playlist_limit == 0 {
+            return Err(ConfigError::Invalid("`playlist-limit` must be at least 1".to_string()));
+        }
+
+        Ok(Config {
+            path: path.to_path_buf(),
+            profile: profile.to_string(),
+            token,
+            owners: raw.owners.unwrap_or_default(),
+            mention_id: raw.mention_id,
+            prefix,
+            intents,
+            shards,
+            activity,
+            status: raw.status.unwrap_or_default(),
+            audit_log,
+            database: raw.database.unwrap_or_else(|| PathBuf::from("skybot.db")),
+            metrics: raw.metrics,
+            error_channel: raw.error_channel,
+            playlist_limit,
+        })
+    }
+}
+
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, why) => write!(f, "Could not read {}: {}", path.display(), why),
+            ConfigError::Parse(path, why) => write!(f, "{} is not a valid config file: {}", path.display(), why),
+            ConfigError::Env(key, why) => write!(f, "Invalid environment variable {}: {}", key, why),
+            ConfigError::UnknownProfile(name, known) => write!(f, "Unknown profile `{}`, available profiles: {}", name, known.join(", ")),
+            ConfigError::Invalid(why) => write!(f, "Invalid config: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(json: &str) -> RawConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn build(json: &str) -> Result<Config, ConfigError> {
+        Config::from_raw(raw(json), Path::new("config.json"), DEFAULT_PROFILE)
+    }
+
+    fn invalid(json: &str) -> String {
+        match build(json) {
+            Err(ConfigError::Invalid(why)) => why,
+            other => panic!("expected an invalid config, got {:?}", other.map(|config| config.profile)),
+        }
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let config = build(r#"{ "token": "abc" }"#).unwrap();
+
+        assert_eq!(config.token, "abc");
+        assert_eq!(config.prefix, "!");
+        assert!(config.owners.is_empty());
+        assert_eq!(config.intents, GatewayIntents::all());
+        assert_eq!(config.shards, ShardsConfig::Auto);
+        assert!(matches!(config.activity.kind, ActivityKind::Watching));
+        assert_eq!(config.activity.text, "OSD");
+        assert!(matches!(config.status, StatusKind::Idle));
+        assert_eq!(config.audit_log.dir, PathBuf::from("logs"));
+        assert_eq!(config.database, PathBuf::from("skybot.db"));
+        assert_eq!(config.playlist_limit, 100);
+        assert!(config.metrics.is_none());
+    }
+
+    #[test]
+    fn reads_every_field() {
+        let config = build(r#"{
+            "token": "abc",
+            "owners": [1, 2],
+            "prefix": "?",
+            "intents": ["guilds", "guild-messages"],
+            "shards": { "first": 0, "last": 1, "total": 4 },
+            "activity": { "kind": "playing", "text": "music" },
+            "status": "dnd",
+            "audit-log": { "max-files": 5 },
+            "metrics": "127.0.0.1:9100",
+            "playlist-limit": 25
+        }"#).unwrap();
+
+        assert_eq!(config.owners, vec![UserId(1), UserId(2)]);
+        assert_eq!(config.prefix, "?");
+        assert_eq!(config.intents, GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES);
+        assert_eq!(config.shards, ShardsConfig::Range { first: 0, last: 1, total: 4 });
+        assert!(matches!(config.activity.kind, ActivityKind::Playing));
+        assert_eq!(config.activity.text, "music");
+        assert!(matches!(config.status, StatusKind::Dnd));
+        assert_eq!(config.audit_log.max_files, 5);
+        assert_eq!(config.audit_log.max_size_mb, 10);
+        assert_eq!(config.metrics, Some("127.0.0.1:9100".parse().unwrap()));
+        assert_eq!(config.playlist_limit, 25);
+    }
+
+    #[test]
+    fn rejects_a_missing_or_empty_token() {
+        assert!(invalid("{}").contains("no `token`"));
+        assert!(invalid(r#"{ "token": "  " }"#).contains("`token` must not be empty"));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(invalid(r#"{ "token": "abc", "prefix": "" }"#).contains("`prefix`"));
+        assert!(invalid(r#"{ "token": "abc", "prefix": "a b" }"#).contains("`prefix`"));
+        assert!(invalid(r#"{ "token": "abc", "intents": ["GUILDS", "NOPE"] }"#).contains("unknown intent `NOPE`"));
+        assert!(invalid(r#"{ "token": "abc", "intents": [] }"#).contains("at least one intent"));
+        assert!(invalid(r#"{ "token": "abc", "shards": 0 }"#).contains("`shards`"));
+        assert!(invalid(r#"{ "token": "abc", "shards": "many" }"#).contains("`shards`"));
+        assert!(invalid(r#"{ "token": "abc", "shards": { "first": 2, "last": 4, "total": 4 } }"#).contains("does not fit"));
+        assert!(invalid(r#"{ "token": "abc", "activity": { "text": " " } }"#).contains("`activity.text`"));
+        assert!(invalid(r#"{ "token": "abc", "audit-log": { "max-files": 0 } }"#).contains("`audit-log"));
+        assert!(invalid(r#"{ "token": "abc", "playlist-limit": 0 }"#).contains("`playlist-limit`"));
+    }
+
+    #[test]
+    fn rejects_unknown_and_mistyped_fields() {
+        assert!(serde_json::from_str::<RawConfig>(r#"{ "token": "abc", "prefx": "!" }"#).is_err());
+        assert!(serde_json::from_str::<RawConfig>(r#"{ "token": "abc", "owners": "me" }"#).is_err());
+        assert!(serde_json::from_str::<RawConfig>(r#"{ "token": "abc", "activity": { "kind": "dancing", "text": "x" } }"#).is_err());
+    }
+
+    /// Mirrors `Config::load`, with `env` standing in for the `SKYBOT_*` variables
+    fn layered(json: &str, profile: &str, env: RawProfile) -> Result<Config, ConfigError> {
+        let mut raw = raw(json);
+        raw.select_profile(profile)?;
+        raw.overlay(env);
+
+        Config::from_raw(raw, Path::new("config.json"), profile)
+    }
+
+    const PROFILES: &str = r#"{
+        "token": "prod-token",
+        "prefix": "!",
+        "activity": { "kind": "listening", "text": "music" },
+        "audit-log": { "dir": "logs", "max-files": 3 },
+        "profiles": {
+            "staging": {
+                "token": "staging-token",
+                "prefix": "?",
+                "activity": { "text": "staging" },
+                "audit-log": { "max-files": 7 }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn selects_a_named_profile() {
+        let config = layered(PROFILES, "staging", RawProfile::default()).unwrap();
+
+        assert_eq!(config.profile, "staging");
+        assert_eq!(config.token, "staging-token");
+        assert_eq!(config.prefix, "?");
+
+        let config = layered(PROFILES, DEFAULT_PROFILE, RawProfile::default()).unwrap();
+
+        assert_eq!(config.token, "prod-token");
+        assert_eq!(config.prefix, "!");
+    }
+
+    #[test]
+    fn profiles_replace_single_activity_and_audit_log_fields() {
+        let config = layered(PROFILES, "staging", RawProfile::default()).unwrap();
+
+        assert!(matches!(config.activity.kind, ActivityKind::Listening));
+        assert_eq!(config.activity.text, "staging");
+        assert_eq!(config.audit_log.dir, PathBuf::from("logs"));
+        assert_eq!(config.audit_log.max_files, 7);
+    }
+
+    #[test]
+    fn test_profile_falls_back_to_test_token() {
+        let config = layered(r#"{ "token": "prod-token", "test-token": "test-token" }"#, "test", RawProfile::default()).unwrap();
+        assert_eq!(config.token, "test-token");
+
+        let json = r#"{ "token": "prod-token", "test-token": "old", "profiles": { "test": { "token": "new" } } }"#;
+        assert_eq!(layered(json, "test", RawProfile::default()).unwrap().token, "new");
+    }
+
+    #[test]
+    fn rejects_an_unknown_profile() {
+        match layered(PROFILES, "dev", RawProfile::default()) {
+            Err(ConfigError::UnknownProfile(name, known)) => {
+                assert_eq!(name, "dev");
+                assert_eq!(known, vec!["prod".to_string(), "staging".to_string()]);
+            },
+            other => panic!("expected an unknown profile, got {:?}", other.map(|config| config.profile)),
+        }
+
+        assert!(matches!(layered(r#"{ "token": "abc" }"#, "test", RawProfile::default()), Err(ConfigError::UnknownProfile(..))));
+    }
+
+    #[test]
+    fn environment_overrides_the_profile() {
+        let env = RawProfile {
+            prefix: Some("$".to_string()),
+            activity: Some(RawActivity { kind: Some(ActivityKind::Competing), ..Default::default() }),
+            audit_log: Some(RawAuditLog { dir: Some(PathBuf::from("/var/log/skybot")), ..Default::default() }),
+            ..Default::default()
+        };
+
+        let config = layered(PROFILES, "staging", env).unwrap();
+
+        assert_eq!(config.token, "staging-token");
+        assert_eq!(config.prefix, "$");
+        assert!(matches!(config.activity.kind, ActivityKind::Competing));
+        assert_eq!(config.activity.text, "staging");
+        assert_eq!(config.audit_log.dir, PathBuf::from("/var/log/skybot"));
+        assert_eq!(config.audit_log.max_files, 7);
+    }
+
+    #[test]
+    fn overrides_are_still_validated() {
+        let env = RawProfile { prefix: Some("a b".to_string()), ..Default::default() };
+        assert!(matches!(layered(PROFILES, "staging", env), Err(ConfigError::Invalid(_))));
+
+        let json = r#"{ "token": "abc", "profiles": { "broken": { "playlist-limit": 0 } } }"#;
+        assert!(matches!(layered(json, "broken", RawProfile::default()), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn parses_shards_from_the_environment() {
+        assert!(matches!(RawShards::from_env("4"), Some(RawShards::Count(4))));
+        assert!(matches!(RawShards::from_env(" Auto "), Some(RawShards::Preset(preset)) if preset == "auto"));
+        assert!(matches!(
+            RawShards::from_env("0-3/8"),
+            Some(RawShards::Range(RawShardRange { first: 0, last: 3, total: 8 }))
+        ));
+        assert!(RawShards::from_env("0-x/8").is_none());
+        assert!(RawShards::from_env("3/8").is_none());
+    }
+
+    // The only test that touches the process environment, so nothing else races with it
+    #[test]
+    fn reads_overrides_from_skybot_variables() {
+        env::set_var("SKYBOT_TOKEN", "env-token");
+        env::set_var("SKYBOT_OWNERS", "1, 2");
+        env::set_var("SKYBOT_SHARDS", "0-1/2");
+        env::set_var("SKYBOT_PREFIX", "");
+
+        let profile = RawProfile::from_env().unwrap();
+
+        assert_eq!(profile.token.as_deref(), Some("env-token"));
+        assert_eq!(profile.owners, Some(vec![UserId(1), UserId(2)]));
+        assert!(matches!(profile.shards, Some(RawShards::Range(RawShardRange { first: 0, last: 1, total: 2 }))));
+        assert!(profile.prefix.is_none());
+
+        env::set_var("SKYBOT_OWNERS", "me");
+        assert!(matches!(RawProfile::from_env(), Err(ConfigError::Env("SKYBOT_OWNERS", _))));
+
+        for key in ["SKYBOT_TOKEN", "SKYBOT_OWNERS", "SKYBOT_SHARDS", "SKYBOT_PREFIX"] {
+            env::remove_var(key);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::config::bot_config::DEFAULT_PROFILE;
+
+
+/// Command line options. Each flag can also be set through the environment variable next to it.
+#[derive(Debug, Parser)]
+#[command(name = "skybot", version, about = "A Discord music and utility bot")]
+pub struct Cli {
+    /// Path to the config file
+    #[arg(short, long, env = "SKYBOT_CONFIG", default_value = "config.json")]
+    pub config: PathBuf,
+
+    /// Profile to run with, e.g. `prod`, `test`, or any name under `profiles` in the config file
+    #[arg(short, long, env = "SKYBOT_PROFILE", default_value = DEFAULT_PROFILE)]
+    pub profile: String,
+
+    /// Log filter, e.g. `info` or `skybot=debug,serenity=warn`. Falls back to RUST_LOG.
+    #[arg(short, long, env = "SKYBOT_LOG")]
+    pub log_level: Option<String>,
+
+    /// Validate the configuration and exit without connecting to Discord
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Deprecated: the old way to run with the test token, kept working as `--profile test`
+    #[arg(hide = true, value_parser = ["t"])]
+    pub legacy_test: Option<String>,
+}
+
+impl Cli {
+    /// The profile to load, with the deprecated `t` argument standing in for `--profile test`
+    pub fn profile(&self) -> &str {
+        if self.legacy_test.is_some() { "test" } else { &self.profile }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn arguments_are_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_flags() {
+        let cli = Cli::try_parse_from(["skybot", "--config", "bot.json", "-p", "staging", "-l", "debug", "--dry-run"]).unwrap();
+
+        assert_eq!(cli.config, PathBuf::from("bot.json"));
+        assert_eq!(cli.profile(), "staging");
+        assert_eq!(cli.log_level.as_deref(), Some("debug"));
+        assert!(cli.dry_run);
+    }
+
+    #[test]
+    fn legacy_t_selects_the_test_profile() {
+        let cli = Cli::try_parse_from(["skybot", "t"]).unwrap();
+        assert_eq!(cli.profile(), "test");
+
+        // `t` wins over --profile, as it did before profiles existed
+        let cli = Cli::try_parse_from(["skybot", "--profile", "staging", "t"]).unwrap();
+        assert_eq!(cli.profile(), "test");
+
+        assert!(Cli::try_parse_from(["skybot", "test"]).is_err());
+    }
+}
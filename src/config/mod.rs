@@ -0,0 +1,2 @@
+pub mod bot_config;
+pub mod cli;
\ No newline at end of file
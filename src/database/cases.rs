@@ -0,0 +1,106 @@
+use chrono::Utc;
+use rusqlite::{params, OptionalExtension, Row};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+
+use crate::database::connection::Database;
+
+
+/// A numbered moderation action. Case numbers count up from 1 in each guild.
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub case_id: i64,
+    pub action: String,
+    /// Mention of whoever or whatever the action was taken on, e.g. `<@123>` or `<#456>`
+    pub target: String,
+    pub moderator_id: UserId,
+    pub reason: String,
+    /// Unix timestamp, in seconds
+    pub created_at: i64,
+    /// The case's embed in the mod-log channel, if it was posted
+    pub message_id: Option<MessageId>,
+    /// The mod-log channel the embed was posted in, which may since have changed.
+    /// Cases posted before this was stored don't have it.
+    pub channel_id: Option<ChannelId>,
+}
+
+impl Case {
+    fn from_row(row: &Row) -> rusqlite::Result<Case> {
+        Ok(Case {
+            case_id: row.get(0)?,
+            action: row.get(1)?,
+            target: row.get(2)?,
+            moderator_id: UserId(row.get(3)?),
+            reason: row.get(4)?,
+            created_at: row.get(5)?,
+            message_id: row.get::<_, Option<u64>>(6)?.map(MessageId),
+            channel_id: row.get::<_, Option<u64>>(7)?.map(ChannelId),
+        })
+    }
+}
+
+
+impl Database {
+    /// Stores a new case under the guild's next case number
+    pub fn create_case(&self, guild_id: GuildId, action: &str, target: &str, moderator_id: UserId, reason: &str) -> rusqlite::Result<Case> {
+        self.with(|conn| {
+            let case_id: i64 = conn.query_row(
+                "SELECT COALESCE(MAX(case_id), 0) + 1 FROM cases WHERE guild_id = ?1",
+                params![guild_id.0],
+                |row| row.get(0),
+            )?;
+
+            let created_at = Utc::now().timestamp();
+
+            conn.execute(
+                "INSERT INTO cases (guild_id, case_id, action, target, moderator_id, reason, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![guild_id.0, case_id, action, target, moderator_id.0, reason, created_at],
+            )?;
+
+            Ok(Case {
+                case_id,
+                action: action.to_string(),
+                target: target.to_string(),
+                moderator_id,
+                reason: reason.to_string(),
+                created_at,
+                message_id: None,
+                channel_id: None,
+            })
+        })
+    }
+
+    pub fn case(&self, guild_id: GuildId, case_id: i64) -> rusqlite::Result<Option<Case>> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT case_id, action, target, moderator_id, reason, created_at, message_id, channel_id
+                FROM cases WHERE guild_id = ?1 AND case_id = ?2",
+                params![guild_id.0, case_id],
+                Case::from_row,
+            )
+            .optional()
+        })
+    }
+
+    pub fn set_case_reason(&self, guild_id: GuildId, case_id: i64, reason: &str) -> rusqlite::Result<()> {
+        self.with(|conn| {
+            conn.execute(
+                "UPDATE cases SET reason = ?3 WHERE guild_id = ?1 AND case_id = ?2",
+                params![guild_id.0, case_id, reason],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    pub fn set_case_message(&self, guild_id: GuildId, case_id: i64, channel_id: ChannelId, message_id: MessageId) -> rusqlite::Result<()> {
+        self.with(|conn| {
+            conn.execute(
+                "UPDATE cases SET channel_id = ?3, message_id = ?4 WHERE guild_id = ?1 AND case_id = ?2",
+                params![guild_id.0, case_id, channel_id.0, message_id.0],
+            )?;
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::Connection;
+use serenity::prelude::TypeMapKey;
+
+
+/// Shares the database handle through the client's data map
+pub struct DatabaseContainer;
+
+impl TypeMapKey for DatabaseContainer {
+    type Value = Database;
+}
+
+/// Schema changes, applied in order. Each entry runs once and is tracked with `PRAGMA user_version`,
+/// so new changes must be appended and existing ones never edited.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        prefix TEXT
+    );",
+    "CREATE TABLE warnings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        moderator_id INTEGER NOT NULL,
+        reason TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX warnings_guild_user ON warnings (guild_id, user_id);
+    CREATE TABLE escalation_rules (
+        guild_id INTEGER NOT NULL,
+        warnings INTEGER NOT NULL,
+        action TEXT NOT NULL,
+        duration INTEGER,
+        PRIMARY KEY (guild_id, warnings)
+    );",
+    "ALTER TABLE guild_settings ADD COLUMN modlog_channel INTEGER;
+    CREATE TABLE cases (
+        guild_id INTEGER NOT NULL,
+        case_id INTEGER NOT NULL,
+        action TEXT NOT NULL,
+        target TEXT NOT NULL,
+        moderator_id INTEGER NOT NULL,
+        reason TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        message_id INTEGER,
+        PRIMARY KEY (guild_id, case_id)
+    );",
+    "CREATE TABLE errors (
+        id TEXT PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        guild_id INTEGER,
+        channel_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        command TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        message TEXT NOT NULL,
+        details TEXT NOT NULL
+    );",
+    "CREATE TABLE saved_queues (
+        guild_id INTEGER PRIMARY KEY,
+        voice_channel INTEGER NOT NULL,
+        text_channel INTEGER NOT NULL,
+        position_ms INTEGER NOT NULL,
+        tracks TEXT NOT NULL,
+        saved_at INTEGER NOT NULL
+    );",
+    "ALTER TABLE guild_settings ADD COLUMN track_style TEXT;",
+    "ALTER TABLE guild_settings ADD COLUMN skip_vote_threshold INTEGER;",
+    "ALTER TABLE guild_settings ADD COLUMN dj_role INTEGER;",
+    "ALTER TABLE guild_settings ADD COLUMN volume INTEGER;",
+    "ALTER TABLE guild_settings ADD COLUMN normalize INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE cases ADD COLUMN channel_id INTEGER;",
+];
+
+
+/// Handle to the embedded SQLite database. Cheap to clone.
+#[derive(Clone)]
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    /// Opens (or creates) the database file at `path` and brings its schema up to date
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Database> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+
+        Ok(Database { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    /// Runs `f` with exclusive access to the connection. Keep `f` short, it blocks the calling task.
+    pub fn with<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+        let conn = self.conn.lock().unwrap();
+        f(&conn)
+    }
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::id::GuildId;
+
+    use super::*;
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn new_database_gets_every_migration() {
+        let db = Database::open(":memory:").unwrap();
+
+        assert_eq!(db.with(|conn| Ok(user_version(conn))).unwrap(), MIGRATIONS.len());
+
+        let settings = db.guild_settings(GuildId(1)).unwrap();
+        assert!(settings.prefix.is_none());
+        assert!(!settings.normalize);
+    }
+
+    #[test]
+    fn migrating_again_changes_nothing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute("INSERT INTO guild_settings (guild_id, prefix) VALUES (1, '?')", []).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        let prefix: String = conn.query_row("SELECT prefix FROM guild_settings WHERE guild_id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(prefix, "?");
+    }
+
+    #[test]
+    fn upgrades_an_older_schema_and_keeps_its_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute("INSERT INTO guild_settings (guild_id, prefix) VALUES (1, '?')", []).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        let (prefix, normalize): (String, bool) = conn.query_row(
+            "SELECT prefix, normalize FROM guild_settings WHERE guild_id = 1", [], |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(prefix, "?");
+        assert!(!normalize);
+    }
+
+    #[test]
+    fn failed_migration_leaves_the_version_alone() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // `guild_settings` already exists, so the first migration fails
+        conn.execute_batch("CREATE TABLE guild_settings (guild_id INTEGER PRIMARY KEY);").unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+        assert_eq!(user_version(&conn), 0);
+    }
+}
@@ -0,0 +1,61 @@
+use rusqlite::{params, OptionalExtension};
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+
+use crate::database::connection::Database;
+
+
+/// Everything we know about a failed command, looked up by the ID its user was shown
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    pub id: String,
+    /// Unix timestamp, in seconds
+    pub created_at: i64,
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    pub command: String,
+    pub kind: String,
+    /// The message that invoked the command
+    pub message: String,
+    pub details: String,
+}
+
+impl Database {
+    pub fn record_error(&self, report: &ErrorReport) -> rusqlite::Result<()> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO errors (id, created_at, guild_id, channel_id, user_id, command, kind, message, details)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    report.id, report.created_at, report.guild_id.map(|id| id.0), report.channel_id.0,
+                    report.user_id.0, report.command, report.kind, report.message, report.details,
+                ],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Looks up an error by ID, ignoring case
+    pub fn error_report(&self, id: &str) -> rusqlite::Result<Option<ErrorReport>> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT id, created_at, guild_id, channel_id, user_id, command, kind, message, details
+                FROM errors WHERE id = ?1",
+                params![id.to_uppercase()],
+                |row| Ok(ErrorReport {
+                    id: row.get(0)?,
+                    created_at: row.get(1)?,
+                    guild_id: row.get::<_, Option<u64>>(2)?.map(GuildId),
+                    channel_id: ChannelId(row.get(3)?),
+                    user_id: UserId(row.get(4)?),
+                    command: row.get(5)?,
+                    kind: row.get(6)?,
+                    message: row.get(7)?,
+                    details: row.get(8)?,
+                }),
+            )
+            .optional()
+        })
+    }
+}
@@ -0,0 +1,114 @@
+use rusqlite::{params, OptionalExtension, ToSql};
+use serenity::model::prelude::{ChannelId, GuildId, RoleId};
+use serenity::prelude::*;
+
+use crate::config::bot_config::ConfigContainer;
+use crate::database::connection::{Database, DatabaseContainer};
+use crate::utils::track_display::TrackStyle;
+
+
+/// Settings a guild has changed from the defaults. `None` means "use the default".
+#[derive(Debug, Clone, Default)]
+pub struct GuildSettings {
+    pub prefix: Option<String>,
+    pub modlog_channel: Option<ChannelId>,
+    pub track_style: Option<TrackStyle>,
+    /// Percentage of listeners who must vote to skip a track. `None` lets anyone skip straight away.
+    pub skip_vote_threshold: Option<u8>,
+    /// Members with this role can run the music commands that affect everyone listening
+    pub dj_role: Option<RoleId>,
+    /// Volume new tracks start at, in percent
+    pub volume: Option<u16>,
+    /// Whether new tracks are loudness normalized
+    pub normalize: bool,
+}
+
+impl Database {
+    pub fn guild_settings(&self, guild_id: GuildId) -> rusqlite::Result<GuildSettings> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT prefix, modlog_channel, track_style, skip_vote_threshold, dj_role, volume, normalize FROM guild_settings WHERE guild_id = ?1",
+                params![guild_id.0],
+                |row| Ok(GuildSettings {
+                    prefix: row.get(0)?,
+                    modlog_channel: row.get::<_, Option<u64>>(1)?.map(ChannelId),
+                    track_style: row.get::<_, Option<String>>(2)?.and_then(|style| style.parse().ok()),
+                    skip_vote_threshold: row.get(3)?,
+                    dj_role: row.get::<_, Option<u64>>(4)?.map(RoleId),
+                    volume: row.get(5)?,
+                    normalize: row.get(6)?,
+                }),
+            )
+            .optional()
+            .map(Option::unwrap_or_default)
+        })
+    }
+
+    /// Like `guild_settings`, but logs a failed read and falls back to the defaults
+    pub fn guild_settings_or_default(&self, guild_id: GuildId) -> GuildSettings {
+        self.guild_settings(guild_id).unwrap_or_else(|why| {
+            tracing::warn!("Could not read settings for guild {}: {:?}", guild_id, why);
+            GuildSettings::default()
+        })
+    }
+
+    pub fn set_prefix(&self, guild_id: GuildId, prefix: Option<&str>) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "prefix", prefix)
+    }
+
+    pub fn set_modlog_channel(&self, guild_id: GuildId, channel_id: Option<ChannelId>) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "modlog_channel", channel_id.map(|id| id.0))
+    }
+
+    pub fn set_track_style(&self, guild_id: GuildId, style: Option<TrackStyle>) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "track_style", style.map(TrackStyle::as_str))
+    }
+
+    pub fn set_skip_vote_threshold(&self, guild_id: GuildId, threshold: Option<u8>) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "skip_vote_threshold", threshold)
+    }
+
+    pub fn set_dj_role(&self, guild_id: GuildId, role_id: Option<RoleId>) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "dj_role", role_id.map(|id| id.0))
+    }
+
+    pub fn set_volume(&self, guild_id: GuildId, volume: Option<u16>) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "volume", volume)
+    }
+
+    pub fn set_normalize(&self, guild_id: GuildId, normalize: bool) -> rusqlite::Result<()> {
+        self.set_guild_setting(guild_id, "normalize", normalize)
+    }
+
+    /// Upserts a single column. `column` is always one of our own constants, never user input.
+    fn set_guild_setting(&self, guild_id: GuildId, column: &'static str, value: impl ToSql) -> rusqlite::Result<()> {
+        self.with(|conn| {
+            conn.execute(
+                &format!(
+                    "INSERT INTO guild_settings (guild_id, {column}) VALUES (?1, ?2)
+                    ON CONFLICT(guild_id) DO UPDATE SET {column} = excluded.{column}"
+                ),
+                params![guild_id.0, value],
+            )?;
+
+            Ok(())
+        })
+    }
+}
+
+
+/// The prefix commands use in `guild_id`, falling back to the configured default outside guilds or when unset
+pub async fn prefix(ctx: &Context, guild_id: Option<GuildId>) -> String {
+    let data = ctx.data.read().await;
+
+    if let Some(guild_id) = guild_id {
+        let database = data.get::<DatabaseContainer>().unwrap();
+
+        if let Some(prefix) = database.guild_settings_or_default(guild_id).prefix {
+            return prefix;
+        }
+    }
+
+    let config = data.get::<ConfigContainer>().unwrap().read().await;
+    config.prefix.clone()
+}
@@ -0,0 +1,6 @@
+pub mod cases;
+pub mod connection;
+pub mod errors;
+pub mod guild_settings;
+pub mod saved_queues;
+pub mod warnings;
\ No newline at end of file
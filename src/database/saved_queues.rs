@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use rusqlite::params;
+use serenity::model::prelude::{ChannelId, GuildId};
+
+use crate::database::connection::Database;
+
+
+/// A guild's queue as it was when the bot shut down, so it can pick up where it left off
+#[derive(Debug, Clone)]
+pub struct SavedQueue {
+    pub guild_id: GuildId,
+    pub voice_channel: ChannelId,
+    pub text_channel: ChannelId,
+    /// How far into the first track playback had got
+    pub position: Duration,
+    /// Source URLs, starting with the track that was playing
+    pub tracks: Vec<String>,
+}
+
+impl Database {
+    pub fn save_queue(&self, queue: &SavedQueue) -> rusqlite::Result<()> {
+        let tracks = serde_json::to_string(&queue.tracks).unwrap();
+
+        self.with(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO saved_queues (guild_id, voice_channel, text_channel, position_ms, tracks, saved_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    queue.guild_id.0, queue.voice_channel.0, queue.text_channel.0,
+                    queue.position.as_millis() as u64, tracks, Utc::now().timestamp(),
+                ],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Removes and returns the guild's saved queue, if it has one
+    pub fn take_saved_queue(&self, guild_id: GuildId) -> rusqlite::Result<Option<SavedQueue>> {
+        self.with(|conn| {
+            let mut statement = conn.prepare(
+                "DELETE FROM saved_queues WHERE guild_id = ?1
+                RETURNING guild_id, voice_channel, text_channel, position_ms, tracks",
+            )?;
+
+            let mut rows = statement.query_map(params![guild_id.0], |row| {
+                let tracks: String = row.get(4)?;
+
+                Ok(SavedQueue {
+                    guild_id: GuildId(row.get(0)?),
+                    voice_channel: ChannelId(row.get(1)?),
+                    text_channel: ChannelId(row.get(2)?),
+                    position: Duration::from_millis(row.get(3)?),
+                    tracks: serde_json::from_str(&tracks).unwrap_or_default(),
+                })
+            })?;
+
+            rows.next().transpose()
+        })
+    }
+}
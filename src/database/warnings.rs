@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use rusqlite::params;
+use serenity::model::prelude::{GuildId, UserId};
+
+use crate::database::connection::Database;
+
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub id: i64,
+    pub user_id: UserId,
+    pub moderator_id: UserId,
+    pub reason: String,
+    /// Unix timestamp, in seconds
+    pub created_at: i64,
+}
+
+/// Action taken automatically once a member reaches `warnings` warnings
+#[derive(Debug, Clone, Copy)]
+pub struct EscalationRule {
+    pub warnings: u32,
+    pub action: EscalationAction,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EscalationAction {
+    Timeout(Duration),
+    Kick,
+    Ban,
+}
+
+impl EscalationAction {
+    fn from_row(action: &str, duration: Option<u64>) -> Option<EscalationAction> {
+        match (action, duration) {
+            ("timeout", Some(secs)) => Some(EscalationAction::Timeout(Duration::from_secs(secs))),
+            ("kick", _) => Some(EscalationAction::Kick),
+            ("ban", _) => Some(EscalationAction::Ban),
+            _ => None,
+        }
+    }
+
+    fn to_row(self) -> (&'static str, Option<u64>) {
+        match self {
+            EscalationAction::Timeout(duration) => ("timeout", Some(duration.as_secs())),
+            EscalationAction::Kick => ("kick", None),
+            EscalationAction::Ban => ("ban", None),
+        }
+    }
+}
+
+
+impl Database {
+    /// Stores a warning and returns its ID
+    pub fn add_warning(&self, guild_id: GuildId, user_id: UserId, moderator_id: UserId, reason: &str) -> rusqlite::Result<i64> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO warnings (guild_id, user_id, moderator_id, reason, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![guild_id.0, user_id.0, moderator_id.0, reason, Utc::now().timestamp()],
+            )?;
+
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// A member's warnings in this guild, oldest first
+    pub fn warnings(&self, guild_id: GuildId, user_id: UserId) -> rusqlite::Result<Vec<Warning>> {
+        self.with(|conn| {
+            let mut statement = conn.prepare(
+                "SELECT id, user_id, moderator_id, reason, created_at FROM warnings
+                WHERE guild_id = ?1 AND user_id = ?2 ORDER BY id",
+            )?;
+
+            let rows = statement.query_map(params![guild_id.0, user_id.0], |row| Ok(Warning {
+                id: row.get(0)?,
+                user_id: UserId(row.get(1)?),
+                moderator_id: UserId(row.get(2)?),
+                reason: row.get(3)?,
+                created_at: row.get(4)?,
+            }))?;
+
+            rows.collect()
+        })
+    }
+
+    /// Deletes a warning by ID, returning it if it existed in this guild
+    pub fn delete_warning(&self, guild_id: GuildId, id: i64) -> rusqlite::Result<Option<Warning>> {
+        self.with(|conn| {
+            let mut statement = conn.prepare(
+                "DELETE FROM warnings WHERE guild_id = ?1 AND id = ?2
+                RETURNING id, user_id, moderator_id, reason, created_at",
+            )?;
+
+            let mut rows = statement.query_map(params![guild_id.0, id], |row| Ok(Warning {
+                id: row.get(0)?,
+                user_id: UserId(row.get(1)?),
+                moderator_id: UserId(row.get(2)?),
+                reason: row.get(3)?,
+                created_at: row.get(4)?,
+            }))?;
+
+            rows.next().transpose()
+        })
+    }
+
+    /// Deletes all of a member's warnings in this guild, returning how many there were
+    pub fn clear_warnings(&self, guild_id: GuildId, user_id: UserId) -> rusqlite::Result<usize> {
+        self.with(|conn| {
+            conn.execute(
+                "DELETE FROM warnings WHERE guild_id = ?1 AND user_id = ?2",
+                params![guild_id.0, user_id.0],
+            )
+        })
+    }
+
+    /// The guild's escalation rules, ordered by warning count
+    pub fn escalation_rules(&self, guild_id: GuildId) -> rusqlite::Result<Vec<EscalationRule>> {
+        self.with(|conn| {
+            let mut statement = conn.prepare(
+                "SELECT warnings, action, duration FROM escalation_rules WHERE guild_id = ?1 ORDER BY warnings",
+            )?;
+
+            let rows = statement.query_map(params![guild_id.0], |row| {
+                let action: String = row.get(1)?;
+                Ok((row.get::<_, u32>(0)?, EscalationAction::from_row(&action, row.get(2)?)))
+            })?;
+
+            let mut rules = Vec::new();
+
+            for row in rows {
+                if let (warnings, Some(action)) = row? {
+                    rules.push(EscalationRule { warnings, action });
+                }
+            }
+
+            Ok(rules)
+        })
+    }
+
+    pub fn set_escalation_rule(&self, guild_id: GuildId, rule: EscalationRule) -> rusqlite::Result<()> {
+        let (action, duration) = rule.action.to_row();
+
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO escalation_rules (guild_id, warnings, action, duration) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT(guild_id, warnings) DO UPDATE SET action = excluded.action, duration = excluded.duration",
+                params![guild_id.0, rule.warnings, action, duration],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Returns whether a rule existed for that warning count
+    pub fn remove_escalation_rule(&self, guild_id: GuildId, warnings: u32) -> rusqlite::Result<bool> {
+        self.with(|conn| {
+            let removed = conn.execute(
+                "DELETE FROM escalation_rules WHERE guild_id = ?1 AND warnings = ?2",
+                params![guild_id.0, warnings],
+            )?;
+
+            Ok(removed > 0)
+        })
+    }
+}
@@ -1,66 +1,147 @@
-use std::fs;
-use std::io::Write;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use serenity::collector::ReactionCollectorBuilder;
-use serenity::framework::standard::{CommandResult};
+use serenity::framework::standard::{CommandResult, DispatchError, Reason};
 use serenity::framework::standard::macros::{hook};
-use serenity::futures::StreamExt;
 use serenity::model::channel::Message;
 use serenity::prelude::*;
 
-use chrono::Local;
+use chrono::Utc;
+
+use crate::config::bot_config::ConfigContainer;
+use crate::database::guild_settings;
+use crate::utils::audit_log::{AuditLogContainer, AuditRecord, CommandTimings};
+use crate::utils::errors::{self, BotError};
+use crate::utils::metrics::MetricsContainer;
 
 
 #[hook]
-pub async fn before(ctx: &Context, msg: &Message, command_name: &str) -> bool {
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open("cmds")
-        .unwrap();
-
-    if let Err(why) = write!(
-        file,
-        "{} command: Used by {} on {} with arguments \"{}\" in channel {:#?}\n", 
-            command_name, 
-            msg.author.tag(),
-            Local::now().format("%a %v %l:%M:%S %p"),
-            msg.content.trim_end(), 
-            msg.channel_id.name(ctx).await.unwrap()
-    ) {
-        println!("An Internal Error has occured\n{:#?}\n{:#?}", why, why.kind());
-    }
+pub async fn before(ctx: &Context, msg: &Message, _command_name: &str) -> bool {
+    let timings = ctx.data.read().await.get::<CommandTimings>().unwrap().clone();
+    timings.lock().unwrap().insert(msg.id, Instant::now());
+
     true
 }
 
 #[hook]
-pub async fn after(ctx: &Context, msg: &Message, _command_name: &str, command_result: CommandResult) {
-    if let Err(why) = command_result {
-            let print_string = format!("{:#?}\n{:#?}", why, why.source());
-            println!("{}", print_string);
-
-            let message = msg.channel_id.send_message(ctx, |m| 
-                m.content("An Internal Error has occured.")).await.unwrap();
-
-            message.react(ctx, '❔').await;
-
-            let collector = ReactionCollectorBuilder::new(ctx)
-                .message_id(message.clone().id)
-                .timeout(Duration::from_secs(60))
-                .collect_limit(1)
-                .build();
-            
-            let message = &message;
-            let print_string = &print_string as &str;
-
-            let _: Vec<_> = collector.then(|_msg| async move {
-                let mut message = message.clone();
-                message.edit(ctx, |m| 
-                    m.content(format!("```{:#?}```",  print_string))
-                ).await;
-
-                _msg
-            }).collect().await;
-        }
-}
\ No newline at end of file
+pub async fn after(ctx: &Context, msg: &Message, command_name: &str, command_result: CommandResult) {
+    let started = {
+        let timings = ctx.data.read().await.get::<CommandTimings>().unwrap().clone();
+        let started = timings.lock().unwrap().remove(&msg.id);
+        started
+    };
+
+    let latency = started.map(|started| started.elapsed());
+
+    let metrics = ctx.data.read().await.get::<MetricsContainer>().unwrap().clone();
+    metrics.record(command_name, command_result.is_ok(), latency);
+
+    let outcome = match command_result {
+        Ok(()) => AuditOutcome { latency, outcome: "ok", error: None, error_id: None },
+        Err(why) => {
+            let why = BotError::from(why);
+            let error = why.to_string();
+            let error_id = errors::report(ctx, msg, command_name, why).await;
+
+            AuditOutcome { latency, outcome: "error", error: Some(error), error_id: Some(error_id) }
+        },
+    };
+
+    audit(ctx, msg, command_name, outcome).await;
+}
+
+#[hook]
+pub async fn dispatch_error(ctx: &Context, msg: &Message, error: DispatchError, command_name: &str) {
+    let metrics = ctx.data.read().await.get::<MetricsContainer>().unwrap().clone();
+    metrics.record_rejection(command_name);
+
+    audit(ctx, msg, command_name, AuditOutcome {
+        latency: None,
+        outcome: "rejected",
+        error: Some(format!("{:?}", error)),
+        error_id: None,
+    }).await;
+
+    let reply = match error {
+        DispatchError::LackingPermissions(permissions) =>
+            format!("You need the `{}` permission(s) to use this command", permissions.get_permission_names().join("`, `")),
+        DispatchError::NotEnoughArguments { min, given } =>
+            format!("This command needs at least {} argument(s), but got {}", min, given),
+        DispatchError::TooManyArguments { max, given } =>
+            format!("This command takes at most {} argument(s), but got {}", max, given),
+        DispatchError::OnlyForGuilds => "This command can only be used in a server".to_string(),
+        DispatchError::OnlyForOwners => "This command can only be used by the bot's owners".to_string(),
+        DispatchError::CheckFailed(_, Reason::User(reason)) => reason,
+        _ => return,
+    };
+
+    let _ = msg.channel_id.say(ctx, reply).await;
+}
+
+
+struct AuditOutcome {
+    latency: Option<Duration>,
+    outcome: &'static str,
+    error: Option<String>,
+    error_id: Option<String>,
+}
+
+async fn audit(ctx: &Context, msg: &Message, command_name: &str, outcome: AuditOutcome) {
+    let audit_log = ctx.data.read().await.get::<AuditLogContainer>().unwrap().clone();
+
+    let args = command_args(ctx, msg, command_name).await;
+
+    let record = AuditRecord {
+        timestamp: Utc::now().to_rfc3339(),
+        guild_id: msg.guild_id.map(|id| id.0),
+        channel_id: msg.channel_id.0,
+        user_id: msg.author.id.0,
+        command: command_name,
+        args,
+        latency_ms: outcome.latency.map(|latency| latency.as_millis()),
+        outcome: outcome.outcome,
+        error: outcome.error,
+        error_id: outcome.error_id,
+    };
+
+    if let Err(why) = audit_log.write(&record) {
+        println!("Could not write to the audit log: {:?}", why);
+    }
+}
+
+
+/// Everything after the prefix and the command words, read the same way the framework reads the message
+async fn command_args<'a>(ctx: &Context, msg: &'a Message, command_name: &str) -> &'a str {
+    let mention_id = ctx.data.read().await.get::<ConfigContainer>().unwrap().read().await.mention_id
+        .unwrap_or_else(|| ctx.cache.current_user_id());
+
+    let content = msg.content.trim_end();
+    let mentioned = content.strip_prefix(&format!("<@{}>", mention_id))
+        .or_else(|| content.strip_prefix(&format!("<@!{}>", mention_id)));
+
+    let rest = match mentioned {
+        Some(rest) => rest.trim_start(),
+        None => {
+            let prefix = guild_settings::prefix(ctx, msg.guild_id).await.to_lowercase();
+
+            // DMs work without a prefix, so a missing one is not an error here
+            content.get(..prefix.len())
+                .filter(|start| start.to_lowercase() == prefix)
+                .map_or(content, |_| &content[prefix.len()..])
+        },
+    };
+
+    // The first word is the command or one of its aliases. Sub-commands are reached through their parent, one word later.
+    let (invoked, rest) = split_word(rest);
+    let (next, after_next) = split_word(rest);
+
+    if !invoked.eq_ignore_ascii_case(command_name) && next.eq_ignore_ascii_case(command_name) {
+        after_next
+    } else {
+        rest
+    }
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    text.split_once(char::is_whitespace)
+        .map_or((text, ""), |(word, rest)| (word, rest.trim_start()))
+}
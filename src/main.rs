@@ -1,62 +1,139 @@
 mod commands;
+mod config;
+mod database;
 mod events;
 mod utils;
 
-use std::collections::HashSet;
-use std::{env, fs};
+use std::collections::{HashMap, HashSet};
+use std::process;
+use std::sync::Arc;
 
+use clap::Parser;
 use serenity::async_trait;
 use serenity::framework::StandardFramework;
+use serenity::http::Http;
+use serenity::model::application::interaction::Interaction;
 use serenity::model::gateway::Ready;
-use serenity::model::prelude::{UserId, Activity};
+use serenity::model::prelude::UserId;
 use serenity::prelude::*;
 
-use songbird::SerenityInit;
+use songbird::{SerenityInit, Songbird};
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::bot_config::{Config, ConfigContainer, ShardsConfig};
+use crate::config::cli::Cli;
+use crate::database::connection::{Database, DatabaseContainer};
+use crate::database::guild_settings;
+use crate::utils::audit_log::{AuditLog, AuditLogContainer, CommandTimings};
+use crate::utils::metrics::{self, Metrics, MetricsContainer};
+use crate::utils::music_sessions::MusicSessions;
+use crate::utils::shutdown::{self, ShardManagerContainer};
 
 struct Events;
 
 #[async_trait]
 impl EventHandler for Events {
     async fn ready(&self, ctx: Context, ready: Ready) {
-        ctx.set_activity(Activity::watching("OSD")).await;
-        ctx.idle().await;
+        let (activity, status) = {
+            let data = ctx.data.read().await;
+            let config = data.get::<ConfigContainer>().unwrap().read().await;
+            (config.activity.to_activity(), config.status.to_online_status())
+        };
+
+        ctx.set_presence(Some(activity), status).await;
+
+        let guilds: Vec<_> = ready.guilds.iter().map(|guild| guild.id).collect();
+        commands::music::restore_queues(&ctx, guilds).await;
 
         println!("{} is ready", ready.user.name);
     }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        commands::slash::handle_interaction(&ctx, interaction).await;
+    }
 }
 
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
-
-    // Read the json data
-    let data: String = fs::read_to_string("config.json")
-        .expect("No config.json file in folder");
-    
-    let cfg: serde_json::Value = serde_json::from_str(&data)
-        .expect("config.json file is not in proper json format");
-    
-    let mut token: String = cfg["token"].clone().to_string().replace("\"", "");
-
-    // Check command arguments to see if test running
-    let args: Vec<String> = env::args().collect();
-
-    if args.iter().any(|i| i == "t") {
-        token = cfg["test-token"].clone().to_string().replace("\"", "");
+    let cli = Cli::parse();
+
+    match &cli.log_level {
+        Some(level) => tracing_subscriber::fmt().with_env_filter(EnvFilter::new(level)).init(),
+        None => tracing_subscriber::fmt::init(),
     }
 
-    // Define used intents
-    let intents = GatewayIntents::all();
+    if cli.legacy_test.is_some() {
+        eprintln!("Running with `t` is deprecated and will be removed, use `--profile test` instead");
+    }
+
+    // Read and validate the config file, then layer the profile and environment on top
+    let config = match Config::load(&cli.config, cli.profile()) {
+        Ok(config) => config,
+        Err(why) => {
+            eprintln!("{}", why);
+            process::exit(1);
+        }
+    };
+
+    if cli.dry_run {
+        println!(
+            "{} is valid (profile `{}`, prefix `{}`, {} owner(s), shards {:?}, intents {:?})",
+            config.path.display(), config.profile, config.prefix, config.owners.len(), config.shards, config.intents
+        );
+        return;
+    }
+
+    let token = config.token.clone();
+    let shards = config.shards;
+
+    let database = match Database::open(&config.database) {
+        Ok(database) => database,
+        Err(why) => {
+            eprintln!("Could not open database {}: {}", config.database.display(), why);
+            process::exit(1);
+        }
+    };
+
+    let audit_log = AuditLog::new(config.audit_log.clone());
+    let metrics = Metrics::default();
+
+    // Bind before connecting so a taken port fails fast instead of after login
+    let metrics_listener = match config.metrics {
+        Some(addr) => match TcpListener::bind(addr).await {
+            Ok(listener) => Some(listener),
+            Err(why) => {
+                eprintln!("Could not serve metrics on {}: {}", addr, why);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
 
     // Define owners
-    let owners: HashSet<UserId> = HashSet::from([UserId::from(667760867483582492), UserId::from(732627312805412884)]);
+    let owners: HashSet<UserId> = config.owners.iter().copied().collect();
+
+    // Use the configured mention id, or ask Discord who the token belongs to
+    let mention_id = match config.mention_id {
+        Some(id) => id,
+        None => match Http::new(&token).get_current_user().await {
+            Ok(user) => user.id,
+            Err(why) => {
+                eprintln!("Could not fetch the bot user: {:?}", why);
+                process::exit(1);
+            }
+        },
+    };
 
     // Define framework
-    let framework = StandardFramework::new()
+    let mut framework = StandardFramework::new()
         .configure(|c| c
-            .on_mention(Some(UserId::from(796491764092633128)))
-            .prefix("!")
+            .on_mention(Some(mention_id))
+            .prefix("")
+            .dynamic_prefix(|ctx, msg| Box::pin(async move {
+                Some(guild_settings::prefix(ctx, msg.guild_id).await)
+            }))
             .with_whitespace(false)
             .owners(owners)
             .case_insensitivity(true)
@@ -65,20 +142,77 @@ async fn main() {
         .help(&commands::help::HELP) // src/commands/help.rs
         .before(events::command_events::before) // src/events/command_events.rs
         .after(events::command_events::after) // src/events/command_events.rs
-        .group(&commands::utility::UTILITIES_GROUP) // src/commands/util.rs
-        .group(&commands::music::MUSIC_GROUP); // src/commands/music.rs
+        .on_dispatch_error(events::command_events::dispatch_error); // src/events/command_events.rs
+
+    for group in commands::GROUPS {
+        framework = framework.group(group);
+    }
+
+    // Kept around so the metrics endpoint can see voice connections
+    let songbird = Songbird::serenity();
 
     // Initialize client
     let mut client = 
-        Client::builder(&token, intents)
+        Client::builder(&token, config.intents)
             .event_handler(Events)
             .framework(framework)
-            .register_songbird()
+            .register_songbird_with(songbird.clone())
             .await
             .expect("Error creating client");
 
+    {
+        let mut data = client.data.write().await;
+        data.insert::<ConfigContainer>(Arc::new(RwLock::new(config)));
+        data.insert::<DatabaseContainer>(database);
+        data.insert::<AuditLogContainer>(audit_log);
+        data.insert::<CommandTimings>(Arc::new(std::sync::Mutex::new(HashMap::new())));
+        data.insert::<MetricsContainer>(metrics.clone());
+        data.insert::<ShardManagerContainer>(client.shard_manager.clone());
+        data.insert::<MusicSessions>(Arc::new(std::sync::Mutex::new(HashMap::new())));
+    }
+
+    // Shut down in order on Ctrl-C or SIGTERM rather than dropping voice connections mid-stream
+    {
+        let data = client.data.clone();
+        let cache_and_http = client.cache_and_http.clone();
+        let songbird = songbird.clone();
+
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            shutdown::shutdown(&data, &cache_and_http.cache, &cache_and_http.http, &songbird).await;
+        });
+    }
+
+    if let Some(listener) = metrics_listener {
+        tokio::spawn(metrics::serve(listener, metrics, client.cache_and_http.cache.clone(), songbird.clone()));
+    }
+
     // Start the client with error handler
-    if let Err(why) = client.start().await {
+    let started = match shards {
+        ShardsConfig::Auto => client.start_autosharded().await,
+        ShardsConfig::Fixed(total) => client.start_shards(total).await,
+        ShardsConfig::Range { first, last, total } => client.start_shard_range([first, last], total).await,
+    };
+
+    if let Err(why) = started {
         println!("Client error: {:?}", why);
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate()).expect("Could not listen for SIGTERM");
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate.recv() => {},
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,442 @@
+//! Where track audio comes from and how it's processed: the guild's volume and ffmpeg filters.
+//! songbird's `Restartable::ytdl` and `Restartable::ffmpeg` can't be given ffmpeg arguments, so
+//! `source` rebuilds the same yt-dlp → ffmpeg pipeline with our filters added.
+//!
+//! Filters that change the speed make the track's time run apart from the original audio's.
+//! Tracks keep the track's time everywhere (positions, durations, seeking), and the source
+//! converts when it has to tell ffmpeg where to start.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::async_trait;
+use serenity::model::channel::Attachment;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::*;
+use songbird::tracks::TrackHandle;
+use songbird::input::error::{Error, Result as SourceResult};
+use songbird::input::restartable::Restart;
+use songbird::input::{children_to_reader, Codec, Container, Input, Metadata, Restartable};
+
+use crate::database::connection::DatabaseContainer;
+use crate::utils::music_sessions::MusicSessions;
+use crate::utils::ytdlp;
+
+
+/// Volume of tracks in guilds that haven't set one, in percent
+pub const DEFAULT_VOLUME: u16 = 100;
+
+pub const MAX_VOLUME: u16 = 200;
+
+/// Single-pass EBU R128 normalization, so quiet and loud tracks come out about as loud as each other
+const LOUDNORM_FILTER: &str = "loudnorm=I=-16:TP=-1.5:LRA=11";
+
+/// The sample rate we have ffmpeg work at, so the pitch filters know what they're changing
+const SAMPLE_RATE: f64 = 48000.0;
+
+/// How far `speed` and `pitch` can go either way. Also the most one `atempo` filter can do.
+pub const MIN_FACTOR: f64 = 0.5;
+pub const MAX_FACTOR: f64 = 2.0;
+
+/// The same format songbird's own sources decode to
+const PCM_ARGS: [&str; 9] = ["-f", "s16le", "-ac", "2", "-ar", "48000", "-acodec", "pcm_f32le", "-"];
+
+/// Directory under the system's temporary one that uploads are saved in while they're queued
+const UPLOAD_DIR: &str = "skybot-uploads";
+
+
+/// How a guild's tracks should sound
+#[derive(Debug, Clone, Copy)]
+pub struct AudioSettings {
+    /// In percent, up to `MAX_VOLUME`
+    pub volume: u16,
+    pub normalize: bool,
+}
+
+impl AudioSettings {
+    /// The volume as songbird takes it, 1.0 being 100%
+    pub fn gain(&self) -> f32 {
+        self.volume as f32 / 100.0
+    }
+}
+
+/// `guild_id`'s audio settings, or the defaults if its settings can't be read
+pub async fn settings(ctx: &Context, guild_id: GuildId) -> AudioSettings {
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    let settings = database.guild_settings_or_default(guild_id);
+
+    AudioSettings {
+        volume: settings.volume.unwrap_or(DEFAULT_VOLUME),
+        normalize: settings.normalize,
+    }
+}
+
+
+/// An effect `!filter` can put on the music
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioFilter {
+    BassBoost,
+    /// Faster and higher
+    Nightcore,
+    /// Slower and lower
+    Vaporwave,
+    /// Pans around the listener's head
+    EightD,
+    /// Plays faster or slower by this factor, keeping the pitch
+    Speed(f64),
+    /// Raises or lowers the pitch by this factor, keeping the speed
+    Pitch(f64),
+    /// Takes out what's in the middle of the mix, usually the vocals
+    Karaoke,
+}
+
+impl AudioFilter {
+    /// Every filter, for listing them
+    pub const NAMES: [&'static str; 7] = ["bassboost", "nightcore", "vaporwave", "8d", "speed", "pitch", "karaoke"];
+
+    /// Reads a filter by name. Only `speed` and `pitch` take a `value`. The error is the reply to send.
+    pub fn parse(name: &str, value: Option<f64>) -> Result<AudioFilter, String> {
+        let factor = |name: &str| match value {
+            Some(value) if (MIN_FACTOR..=MAX_FACTOR).contains(&value) => Ok(value),
+            _ => Err(format!("`{}` needs a number from `{}` to `{}` (e.g. `{} 1.25`)", name, MIN_FACTOR, MAX_FACTOR, name)),
+        };
+
+        match name.to_lowercase().as_str() {
+            "bassboost" => Ok(AudioFilter::BassBoost),
+            "nightcore" => Ok(AudioFilter::Nightcore),
+            "vaporwave" => Ok(AudioFilter::Vaporwave),
+            "8d" => Ok(AudioFilter::EightD),
+            "speed" => factor("speed").map(AudioFilter::Speed),
+            "pitch" => factor("pitch").map(AudioFilter::Pitch),
+            "karaoke" => Ok(AudioFilter::Karaoke),
+            other => Err(format!("There's no filter called `{}`, try one of {}", other, AudioFilter::NAMES.map(|name| format!("`{}`", name)).join(", "))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioFilter::BassBoost => "bassboost",
+            AudioFilter::Nightcore => "nightcore",
+            AudioFilter::Vaporwave => "vaporwave",
+            AudioFilter::EightD => "8d",
+            AudioFilter::Speed(_) => "speed",
+            AudioFilter::Pitch(_) => "pitch",
+            AudioFilter::Karaoke => "karaoke",
+        }
+    }
+
+    /// The ffmpeg filter chain
+    fn chain(self) -> String {
+        match self {
+            AudioFilter::BassBoost => "bass=g=10".to_string(),
+            AudioFilter::Nightcore => resample(1.25),
+            AudioFilter::Vaporwave => resample(0.8),
+            AudioFilter::EightD => "apulsator=hz=0.08".to_string(),
+            AudioFilter::Speed(factor) => format!("atempo={}", factor),
+            AudioFilter::Pitch(factor) => format!("{},atempo={}", resample(factor), 1.0 / factor),
+            AudioFilter::Karaoke => "stereotools=mlev=0.015625".to_string(),
+        }
+    }
+
+    /// How much faster than the original the track plays
+    fn tempo(self) -> f64 {
+        match self {
+            AudioFilter::Nightcore => 1.25,
+            AudioFilter::Vaporwave => 0.8,
+            AudioFilter::Speed(factor) => factor,
+            _ => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for AudioFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioFilter::Speed(factor) | AudioFilter::Pitch(factor) => write!(f, "{} {}x", self.name(), factor),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// Plays the audio back `factor` times as fast, which raises or lowers the pitch with it
+fn resample(factor: f64) -> String {
+    format!("aresample={0},asetrate={1},aresample={0}", SAMPLE_RATE, (SAMPLE_RATE * factor).round())
+}
+
+
+/// Everything ffmpeg does to a track. Kept on each track, so it can be rebuilt when the guild wants something else.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Effects {
+    pub filters: Vec<AudioFilter>,
+    pub normalize: bool,
+}
+
+impl TypeMapKey for Effects {
+    type Value = Effects;
+}
+
+impl Effects {
+    /// The `-af` chain, if there's anything to do
+    fn chain(&self) -> Option<String> {
+        let mut chain = self.filters.iter().map(|filter| filter.chain()).collect::<Vec<_>>();
+
+        // Last, so it evens out whatever the filters did too
+        if self.normalize {
+            chain.push(LOUDNORM_FILTER.to_string());
+        }
+
+        (!chain.is_empty()).then(|| chain.join(","))
+    }
+
+    /// How much faster than the original the track plays
+    pub fn tempo(&self) -> f64 {
+        self.filters.iter().map(|filter| filter.tempo()).product()
+    }
+}
+
+/// The effects new tracks in `guild_id` should have: the session's filters and the guild's normalization setting
+pub async fn effects(ctx: &Context, guild_id: GuildId) -> Effects {
+    let normalize = settings(ctx, guild_id).await.normalize;
+    let sessions = ctx.data.read().await.get::<MusicSessions>().unwrap().clone();
+    let filters = sessions.lock().unwrap().get(&guild_id).map(|session| session.filters.clone()).unwrap_or_default();
+
+    Effects { filters, normalize }
+}
+
+/// What `track` was built with, no effects for tracks from before effects were kept
+pub async fn track_effects(track: &TrackHandle) -> Effects {
+    track.typemap().read().await.get::<Effects>().cloned().unwrap_or_default()
+}
+
+/// What `track` plays, as it was given to `source`
+pub async fn track_target(track: &TrackHandle) -> Option<SourceTarget> {
+    track.typemap().read().await.get::<SourceTarget>().cloned()
+}
+
+/// Keeps what `track` was built from, so it can be rebuilt with other effects
+pub async fn set_track_source(track: &TrackHandle, target: SourceTarget, effects: Effects) {
+    let mut typemap = track.typemap().write().await;
+    typemap.insert::<SourceTarget>(target);
+    typemap.insert::<Effects>(effects);
+}
+
+
+/// What a track plays
+#[derive(Debug, Clone)]
+pub enum SourceTarget {
+    /// Anything yt-dlp can play, including searches such as `ytsearch1:query`
+    Url(String),
+    /// An attachment saved to disk
+    File(Arc<Upload>),
+}
+
+impl TypeMapKey for SourceTarget {
+    type Value = SourceTarget;
+}
+
+/// An attachment saved for playing. The file is deleted once no track plays from it anymore.
+#[derive(Debug)]
+pub struct Upload {
+    path: PathBuf,
+}
+
+impl Upload {
+    /// Downloads `attachment` into the uploads directory. The file is named after the attachment's ID,
+    /// so uploads can't replace each other or anything outside the directory.
+    pub async fn save(attachment: &Attachment) -> serenity::Result<Arc<Upload>> {
+        let data = attachment.download().await?;
+
+        // ffmpeg goes by the extension for some formats, anything odd in it is dropped
+        let extension = Path::new(&attachment.filename).extension()
+            .and_then(|extension| extension.to_str())
+            .filter(|extension| !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        let name = match extension {
+            Some(extension) => format!("{}.{}", attachment.id, extension),
+            None => attachment.id.to_string(),
+        };
+
+        let dir = std::env::temp_dir().join(UPLOAD_DIR);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(name);
+        fs::write(&path, data)?;
+
+        Ok(Arc::new(Upload { path }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        if let Err(why) = fs::remove_file(&self.path) {
+            println!("Could not delete upload {}: {:?}", self.path.display(), why);
+        }
+    }
+}
+
+/// A lazily started source for `target` that can seek and loop, with `effects` applied.
+/// Remember to `set_track_source` on the track made from it.
+pub async fn source(target: SourceTarget, effects: &Effects) -> SourceResult<Input> {
+    let source = FilteredSource {
+        target,
+        filters: effects.chain(),
+        tempo: effects.tempo(),
+        known: None,
+    };
+
+    Ok(Restartable::new(source, true).await?.into())
+}
+
+/// Like `source`, but trusts `metadata` instead of asking yt-dlp, so it starts no processes until it plays.
+/// For filling the queue with many tracks at once.
+pub async fn described_source(target: SourceTarget, effects: &Effects, metadata: Metadata) -> SourceResult<Input> {
+    let source = FilteredSource {
+        target,
+        filters: effects.chain(),
+        tempo: effects.tempo(),
+        known: Some(metadata),
+    };
+
+    Ok(Restartable::new(source, true).await?.into())
+}
+
+/// A new source for `target`, what `track` plays, with `effects` in place of the ones it was built with.
+/// Like `described_source`, it starts no processes until it plays.
+pub async fn rebuilt_source(track: &TrackHandle, target: SourceTarget, effects: &Effects) -> SourceResult<Input> {
+    let had = track_effects(track).await;
+
+    // Keep what we knew about the track, with its length back in the original audio's time
+    let mut metadata = track.metadata().clone();
+    metadata.duration = metadata.duration.map(|duration| duration.mul_f64(had.tempo()));
+
+    described_source(target, effects, metadata).await
+}
+
+struct FilteredSource {
+    target: SourceTarget,
+    filters: Option<String>,
+    tempo: f64,
+    /// Metadata we already have, of the original audio
+    known: Option<Metadata>,
+}
+
+#[async_trait]
+impl Restart for FilteredSource {
+    async fn call_restart(&mut self, time: Option<Duration>) -> SourceResult<Input> {
+        let mut ffmpeg = Command::new("ffmpeg");
+        let mut children = Vec::new();
+
+        // `time` is in the track's time, ffmpeg wants the original's
+        if let Some(time) = time {
+            ffmpeg.args(["-ss", &format!("{:.3}", time.as_secs_f64() * self.tempo)]);
+        }
+
+        match &self.target {
+            SourceTarget::Url(url) => {
+                let mut downloader = Command::new("yt-dlp")
+                    .args(ytdlp::STREAM_ARGS)
+                    .args([url.as_str(), "-o", "-"])
+                    .stdin(Stdio::null())
+                    .stderr(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .spawn()?;
+
+                let stdout = downloader.stdout.take().ok_or(Error::Stdout)?;
+                ffmpeg.args(["-i", "-"]).stdin(stdout);
+                children.push(downloader);
+            },
+            SourceTarget::File(upload) => {
+                ffmpeg.arg("-i").arg(upload.path()).stdin(Stdio::null());
+            },
+        }
+
+        if let Some(filters) = &self.filters {
+            ffmpeg.args(["-af", filters]);
+        }
+
+        children.push(
+            ffmpeg.args(PCM_ARGS)
+                .stderr(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn()?
+        );
+
+        Ok(Input::new(true, children_to_reader::<f32>(children), Codec::FloatPcm, Container::Raw, None))
+    }
+
+    async fn lazy_init(&mut self) -> SourceResult<(Option<Metadata>, Codec, Container)> {
+        let mut metadata = match (self.known.take(), &self.target) {
+            (Some(known), _) => known,
+            (None, SourceTarget::Url(url)) => Metadata::from_ytdl_output(ytdlp::info(url).await?),
+            (None, SourceTarget::File(upload)) => Metadata::from_ffprobe_json(&probe(upload.path()).await?),
+        };
+
+        // Searches would be run again every time the track restarts, and could find something else
+        if let (SourceTarget::Url(_), Some(url)) = (&self.target, &metadata.source_url) {
+            self.target = SourceTarget::Url(url.clone());
+        }
+
+        // ffmpeg is always asked for stereo, whatever the original had
+        metadata.channels = Some(2);
+        metadata.duration = metadata.duration.map(|duration| duration.div_f64(self.tempo));
+
+        Ok((Some(metadata), Codec::FloatPcm, Container::Raw))
+    }
+}
+
+/// What ffprobe knows about the file at `path`
+async fn probe(path: &Path) -> io::Result<serde_json::Value> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(["-v", "quiet", "-of", "json", "-show_format", "-show_streams", "-i"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    serde_json::from_slice(&output.stdout).map_err(io::Error::from)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters() {
+        assert_eq!(AudioFilter::parse("bassboost", None), Ok(AudioFilter::BassBoost));
+        assert_eq!(AudioFilter::parse("Nightcore", None), Ok(AudioFilter::Nightcore));
+        assert_eq!(AudioFilter::parse("8d", None), Ok(AudioFilter::EightD));
+        assert_eq!(AudioFilter::parse("speed", Some(1.25)), Ok(AudioFilter::Speed(1.25)));
+        assert_eq!(AudioFilter::parse("pitch", Some(MIN_FACTOR)), Ok(AudioFilter::Pitch(MIN_FACTOR)));
+        assert_eq!(AudioFilter::parse("pitch", Some(MAX_FACTOR)), Ok(AudioFilter::Pitch(MAX_FACTOR)));
+    }
+
+    #[test]
+    fn every_listed_name_parses() {
+        for name in AudioFilter::NAMES {
+            let filter = AudioFilter::parse(name, Some(1.0)).unwrap();
+            assert_eq!(filter.name(), name);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_filters() {
+        assert!(AudioFilter::parse("echo", None).is_err());
+        assert!(AudioFilter::parse("", None).is_err());
+        assert!(AudioFilter::parse("speed", None).is_err());
+        assert!(AudioFilter::parse("speed", Some(0.49)).is_err());
+        assert!(AudioFilter::parse("pitch", Some(2.01)).is_err());
+        assert!(AudioFilter::parse("pitch", Some(-1.0)).is_err());
+        assert!(AudioFilter::parse("speed", Some(f64::NAN)).is_err());
+    }
+}
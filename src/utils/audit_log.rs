@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use serenity::model::prelude::MessageId;
+use serenity::prelude::TypeMapKey;
+
+use crate::config::bot_config::AuditLogConfig;
+
+
+/// Shares the audit log through the client's data map
+pub struct AuditLogContainer;
+
+impl TypeMapKey for AuditLogContainer {
+    type Value = AuditLog;
+}
+
+/// When each running command started, keyed by the message that invoked it, so `after` can work out latency
+pub struct CommandTimings;
+
+impl TypeMapKey for CommandTimings {
+    type Value = Arc<Mutex<HashMap<MessageId, Instant>>>;
+}
+
+/// One line of the audit log
+#[derive(Debug, Serialize)]
+pub struct AuditRecord<'a> {
+    pub timestamp: String,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub command: &'a str,
+    pub args: &'a str,
+    pub latency_ms: Option<u128>,
+    /// `ok`, `error`, or `rejected` when the framework refused to run the command
+    pub outcome: &'a str,
+    pub error: Option<String>,
+    /// The ID the user was shown, to find the full report with `!error`
+    pub error_id: Option<String>,
+}
+
+
+/// JSON-lines log of command invocations, split into files under `dir` by day and size.
+/// Files are named `commands-<date>.<n>.jsonl` and only created once there is something to write.
+#[derive(Clone)]
+pub struct AuditLog {
+    inner: Arc<Mutex<AuditLogInner>>,
+}
+
+struct AuditLogInner {
+    config: AuditLogConfig,
+    current: Option<OpenFile>,
+}
+
+struct OpenFile {
+    writer: BufWriter<File>,
+    date: NaiveDate,
+    index: u32,
+    size: u64,
+}
+
+impl AuditLog {
+    pub fn new(config: AuditLogConfig) -> AuditLog {
+        AuditLog { inner: Arc::new(Mutex::new(AuditLogInner { config, current: None })) }
+    }
+
+    pub fn write(&self, record: &AuditRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut inner = self.inner.lock().unwrap();
+        let file = inner.file_for(line.len() as u64)?;
+
+        file.writer.write_all(&line)?;
+        file.writer.flush()?;
+        file.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Makes sure everything written so far has reached the disk, e.g. before shutting down
+    pub fn flush(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if let Some(file) = &mut inner.current {
+            file.writer.flush()?;
+            file.writer.get_ref().sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    /// Switches to new settings, e.g. after the config is reloaded. The next record starts a file under the new `dir`.
+    pub fn reconfigure(&self, config: AuditLogConfig) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.config = config;
+        inner.current = None;
+    }
+}
+
+impl AuditLogInner {
+    /// The file the next `incoming` bytes should go to, rotating first if the day changed or it would grow too large
+    fn file_for(&mut self, incoming: u64) -> io::Result<&mut OpenFile> {
+        let today = Utc::now().date_naive();
+        let max_size = self.config.max_size_mb * 1024 * 1024;
+
+        let rotate = match &self.current {
+            Some(file) => file.date != today || (file.size > 0 && file.size + incoming > max_size),
+            None => true,
+        };
+
+        if rotate {
+            let index = match &self.current {
+                Some(file) if file.date == today => file.index + 1,
+                _ => self.last_index(today)?,
+            };
+
+            self.current = Some(self.open(today, index, max_size)?);
+            self.prune()?;
+        }
+
+        Ok(self.current.as_mut().unwrap())
+    }
+
+    fn path(&self, date: NaiveDate, index: u32) -> PathBuf {
+        self.config.dir.join(format!("commands-{}.{}.jsonl", date.format("%Y-%m-%d"), index))
+    }
+
+    /// Highest index already on disk for `date`, so a restart keeps appending where it left off
+    fn last_index(&self, date: NaiveDate) -> io::Result<u32> {
+        let prefix = format!("commands-{}.", date.format("%Y-%m-%d"));
+
+        let index = self.log_files()?
+            .iter()
+            .filter_map(|path| path.file_name()?.to_str()?.strip_prefix(&prefix)?.strip_suffix(".jsonl")?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+
+        Ok(index)
+    }
+
+    /// Opens the file at `index`, moving on to the next one while they are already full
+    fn open(&self, date: NaiveDate, mut index: u32, max_size: u64) -> io::Result<OpenFile> {
+        fs::create_dir_all(&self.config.dir)?;
+
+        loop {
+            let file = OpenOptions::new().create(true).append(true).open(self.path(date, index))?;
+            let size = file.metadata()?.len();
+
+            if size < max_size {
+                return Ok(OpenFile { writer: BufWriter::new(file), date, index, size });
+            }
+
+            index += 1;
+        }
+    }
+
+    /// Deletes the oldest files past `max_files`
+    fn prune(&self) -> io::Result<()> {
+        let mut files = self.log_files()?;
+
+        if files.len() <= self.config.max_files {
+            return Ok(());
+        }
+
+        // Names sort by date, but `.10` would sort before `.9`, so order by modification time instead
+        files.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+
+        for path in &files[..files.len() - self.config.max_files] {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn log_files(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.config.dir) {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(why) => return Err(why),
+        };
+
+        let mut files = Vec::new();
+
+        for entry in entries {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+
+            if name.starts_with("commands-") && name.ends_with(".jsonl") {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+}
@@ -0,0 +1,15 @@
+use serenity::builder::CreateEmbed;
+use serenity::model::user::User;
+use serenity::utils::Colour;
+
+
+/// The gold "something happened" embed used for command feedback, with who asked for it in the footer
+pub fn action_embed<'a>(e: &'a mut CreateEmbed, title: &str, description: impl ToString, invoker: &User) -> &'a mut CreateEmbed {
+    e.title(title)
+        .description(description)
+        .color(Colour::GOLD)
+        .footer(|f|
+            f.text(format!("Invoked by {}", invoker.name))
+                .icon_url(invoker.face())
+        )
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+
+use rand::Rng;
+use serenity::builder::CreateEmbed;
+use serenity::framework::standard::CommandError;
+use serenity::model::channel::Message;
+use serenity::model::error::Error as ModelError;
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use serenity::utils::Colour;
+
+use crate::config::bot_config::ConfigContainer;
+use crate::database::connection::DatabaseContainer;
+use crate::database::errors::ErrorReport;
+
+
+/// Everything a command can fail with, sorted by what we can tell the user about it
+#[derive(Debug)]
+pub enum BotError {
+    Database(rusqlite::Error),
+    Discord(serenity::Error),
+    Voice(songbird::error::JoinError),
+    Track(songbird::tracks::TrackError),
+    Audio(songbird::input::error::Error),
+    Io(std::io::Error),
+    Other(CommandError),
+}
+
+impl BotError {
+    /// Short name of the variant, stored with each report
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BotError::Database(_) => "database",
+            BotError::Discord(_) => "discord",
+            BotError::Voice(_) => "voice",
+            BotError::Track(_) => "track",
+            BotError::Audio(_) => "audio",
+            BotError::Io(_) => "io",
+            BotError::Other(_) => "other",
+        }
+    }
+
+    /// What to tell whoever ran the command. Never includes the error itself.
+    pub fn user_message(&self) -> &'static str {
+        match self {
+            BotError::Database(_) => "Something went wrong reading or saving this server's data.",
+            BotError::Discord(serenity::Error::Model(ModelError::InvalidPermissions(_) | ModelError::Hierarchy)) =>
+                "I don't have the permissions I need to do that here.",
+            BotError::Discord(serenity::Error::Http(why)) => match why.status_code().map(|status| status.as_u16()) {
+                Some(403) => "I don't have the permissions I need to do that here.",
+                Some(404) => "Something this command needed no longer exists.",
+                Some(429) => "Discord is rate limiting me, try again in a moment.",
+                _ => "Discord didn't accept the request, try again in a moment.",
+            },
+            BotError::Discord(_) => "Something went wrong talking to Discord.",
+            BotError::Voice(_) => "Something went wrong with the voice connection. Try making me leave and join again.",
+            BotError::Track(_) => "That track can't be controlled any more, it may have already finished.",
+            BotError::Audio(_) => "That audio couldn't be loaded. Check the link or try a different search.",
+            BotError::Io(_) | BotError::Other(_) => "Something went wrong running that command.",
+        }
+    }
+}
+
+impl From<CommandError> for BotError {
+    /// Commands return boxed errors through `?`, so recover the concrete type where we know it
+    fn from(why: CommandError) -> BotError {
+        let why = match why.downcast::<BotError>() {
+            Ok(why) => return *why,
+            Err(why) => why,
+        };
+
+        let why = match why.downcast::<rusqlite::Error>() {
+            Ok(why) => return BotError::Database(*why),
+            Err(why) => why,
+        };
+
+        let why = match why.downcast::<serenity::Error>() {
+            Ok(why) => return BotError::Discord(*why),
+            Err(why) => why,
+        };
+
+        let why = match why.downcast::<songbird::error::JoinError>() {
+            Ok(why) => return BotError::Voice(*why),
+            Err(why) => why,
+        };
+
+        let why = match why.downcast::<songbird::tracks::TrackError>() {
+            Ok(why) => return BotError::Track(*why),
+            Err(why) => why,
+        };
+
+        let why = match why.downcast::<songbird::input::error::Error>() {
+            Ok(why) => return BotError::Audio(*why),
+            Err(why) => why,
+        };
+
+        match why.downcast::<std::io::Error>() {
+            Ok(why) => BotError::Io(*why),
+            Err(why) => BotError::Other(why),
+        }
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Database(why) => write!(f, "Database error: {}", why),
+            BotError::Discord(why) => write!(f, "Discord error: {}", why),
+            BotError::Voice(why) => write!(f, "Voice error: {}", why),
+            BotError::Track(why) => write!(f, "Track error: {}", why),
+            BotError::Audio(why) => write!(f, "Audio error: {}", why),
+            BotError::Io(why) => write!(f, "IO error: {}", why),
+            BotError::Other(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+impl std::error::Error for BotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BotError::Database(why) => Some(why),
+            BotError::Discord(why) => Some(why),
+            BotError::Voice(why) => Some(why),
+            BotError::Track(why) => Some(why),
+            BotError::Audio(why) => Some(why),
+            BotError::Io(why) => Some(why),
+            BotError::Other(why) => Some(why.as_ref()),
+        }
+    }
+}
+
+
+/// Short random ID users can quote back to us, e.g. `3F9A1C2B`
+fn error_id() -> String {
+    format!("{:08X}", rand::thread_rng().gen::<u32>())
+}
+
+/// Where a failed command was run from, and how
+pub struct ErrorOrigin {
+    /// Unix timestamp, in seconds
+    pub created_at: i64,
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    /// The invoking message, or a description of the slash command and its options
+    pub content: String,
+}
+
+impl ErrorOrigin {
+    pub fn from_message(msg: &Message) -> ErrorOrigin {
+        ErrorOrigin {
+            created_at: msg.timestamp.unix_timestamp(),
+            guild_id: msg.guild_id,
+            channel_id: msg.channel_id,
+            user_id: msg.author.id,
+            content: msg.content.clone(),
+        }
+    }
+}
+
+/// Logs a failed command in full, stores it under a new error ID, and copies it to the
+/// configured error channel if there is one. Returns the error ID.
+pub async fn record(ctx: &Context, origin: ErrorOrigin, command_name: &str, why: &BotError) -> String {
+    let id = error_id();
+
+    // The Debug output already covers the wrapped error, so the chain starts below it
+    let mut details = format!("{:#?}", why);
+    let mut source = std::error::Error::source(why).and_then(|inner| inner.source());
+
+    while let Some(cause) = source {
+        details.push_str(&format!("\nCaused by: {}", cause));
+        source = cause.source();
+    }
+
+    println!("Error {} in {}: {}", id, command_name, details);
+
+    let report = ErrorReport {
+        id: id.clone(),
+        created_at: origin.created_at,
+        guild_id: origin.guild_id,
+        channel_id: origin.channel_id,
+        user_id: origin.user_id,
+        command: command_name.to_string(),
+        kind: why.kind().to_string(),
+        message: origin.content,
+        details,
+    };
+
+    let (database, error_channel) = {
+        let data = ctx.data.read().await;
+        let config = data.get::<ConfigContainer>().unwrap().read().await;
+        (data.get::<DatabaseContainer>().unwrap().clone(), config.error_channel)
+    };
+
+    if let Err(why) = database.record_error(&report) {
+        println!("Could not store error {}: {:?}", id, why);
+    }
+
+    if let Some(channel_id) = error_channel {
+        let sent = channel_id.send_message(ctx, |m|
+            m.embed(|e| error_report_embed(e, &report))
+        ).await;
+
+        if let Err(why) = sent {
+            println!("Could not post error {} to the error channel: {:?}", id, why);
+        }
+    }
+
+    id
+}
+
+/// What the user is told about a failed command
+pub fn user_reply(why: &BotError, id: &str) -> String {
+    format!("{} (Error ID: `{}`)", why.user_message(), id)
+}
+
+/// Records a failed prefix command and tells the user what went wrong in general terms. Returns the error ID.
+pub async fn report(ctx: &Context, msg: &Message, command_name: &str, why: BotError) -> String {
+    let id = record(ctx, ErrorOrigin::from_message(msg), command_name, &why).await;
+    let _ = msg.channel_id.say(ctx, user_reply(&why, &id)).await;
+
+    id
+}
+
+/// Full details of a stored error. Only meant for owners, since it includes internals.
+pub fn error_report_embed<'a>(e: &'a mut CreateEmbed, report: &ErrorReport) -> &'a mut CreateEmbed {
+    let location = match report.guild_id {
+        Some(guild_id) => format!("<#{}> in guild `{}`", report.channel_id.0, guild_id.0),
+        None => "Direct messages".to_string(),
+    };
+
+    // Embed descriptions are capped at 4096 characters, leave room for the code block
+    let details: String = report.details.chars().take(4000).collect();
+    let message: String = report.message.chars().take(1000).collect();
+
+    e.title(format!("Error {} | {}", report.id, report.command))
+        .description(format!("```\n{}\n```", details))
+        .field("Kind", &report.kind, true)
+        .field("User", format!("<@{}>", report.user_id.0), true)
+        .field("Where", location, true)
+        .field("Message", if message.is_empty() { "*(empty)*".to_string() } else { message }, false)
+        .timestamp(Timestamp::from_unix_timestamp(report.created_at).unwrap_or_else(|_| Timestamp::now()))
+        .colour(Colour::RED)
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serenity::cache::Cache;
+use serenity::prelude::TypeMapKey;
+use songbird::Songbird;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+
+/// Shares the command metrics through the client's data map
+pub struct MetricsContainer;
+
+impl TypeMapKey for MetricsContainer {
+    type Value = Metrics;
+}
+
+/// Upper bounds of the command latency histogram, in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Name, help text and value of one of the per-command counters
+type Counter = (&'static str, &'static str, fn(&CommandStats) -> u64);
+
+/// Largest request head we'll read before giving up on a connection
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+
+/// Per-command counters and latency histograms, rendered in the Prometheus text format
+#[derive(Clone, Default)]
+pub struct Metrics {
+    commands: Arc<Mutex<BTreeMap<String, CommandStats>>>,
+}
+
+#[derive(Default)]
+struct CommandStats {
+    invocations: u64,
+    errors: u64,
+    /// Times the framework refused to run the command, e.g. for missing permissions
+    rejections: u64,
+    /// Non-cumulative counts per bucket in `LATENCY_BUCKETS`, with one extra for `+Inf`
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    latency_sum: f64,
+    latency_count: u64,
+}
+
+/// Voice state, read from songbird when scraped
+struct VoiceStats {
+    connections: usize,
+    /// Guild ID and queue length of each connected call
+    queues: Vec<(u64, usize)>,
+}
+
+impl Metrics {
+    /// Counts a command that ran, successfully or not
+    pub fn record(&self, command: &str, ok: bool, latency: Option<Duration>) {
+        let mut commands = self.commands.lock().unwrap();
+        let stats = commands.entry(command.to_string()).or_default();
+
+        stats.invocations += 1;
+
+        if !ok {
+            stats.errors += 1;
+        }
+
+        if let Some(latency) = latency {
+            let secs = latency.as_secs_f64();
+            let bucket = LATENCY_BUCKETS.iter().position(|&bound| secs <= bound).unwrap_or(LATENCY_BUCKETS.len());
+
+            stats.buckets[bucket] += 1;
+            stats.latency_sum += secs;
+            stats.latency_count += 1;
+        }
+    }
+
+    pub fn record_rejection(&self, command: &str) {
+        let mut commands = self.commands.lock().unwrap();
+        commands.entry(command.to_string()).or_default().rejections += 1;
+    }
+
+    fn render(&self, voice: &VoiceStats) -> String {
+        let commands = self.commands.lock().unwrap();
+        let mut out = String::new();
+
+        let counters: [Counter; 3] = [
+            ("skybot_command_invocations_total", "Commands run, whatever their result", |s| s.invocations),
+            ("skybot_command_errors_total", "Commands that returned an error", |s| s.errors),
+            ("skybot_command_rejections_total", "Commands the framework refused to run", |s| s.rejections),
+        ];
+
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+
+            for (command, stats) in commands.iter() {
+                let _ = writeln!(out, "{}{{command=\"{}\"}} {}", name, escape(command), value(stats));
+            }
+        }
+
+        let name = "skybot_command_latency_seconds";
+        let _ = writeln!(out, "# HELP {} Time from a command being dispatched to it finishing", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+
+        for (command, stats) in commands.iter() {
+            let command = escape(command);
+            let mut cumulative = 0;
+
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+                cumulative += count;
+                let _ = writeln!(out, "{}_bucket{{command=\"{}\",le=\"{}\"}} {}", name, command, bound, cumulative);
+            }
+
+            let _ = writeln!(out, "{}_bucket{{command=\"{}\",le=\"+Inf\"}} {}", name, command, stats.latency_count);
+            let _ = writeln!(out, "{}_sum{{command=\"{}\"}} {}", name, command, stats.latency_sum);
+            let _ = writeln!(out, "{}_count{{command=\"{}\"}} {}", name, command, stats.latency_count);
+        }
+
+        let _ = writeln!(out, "# HELP skybot_voice_connections Guilds the bot is connected to a voice channel in");
+        let _ = writeln!(out, "# TYPE skybot_voice_connections gauge");
+        let _ = writeln!(out, "skybot_voice_connections {}", voice.connections);
+
+        let _ = writeln!(out, "# HELP skybot_voice_queue_length Tracks queued in each connected guild, including the one playing");
+        let _ = writeln!(out, "# TYPE skybot_voice_queue_length gauge");
+
+        for (guild_id, length) in &voice.queues {
+            let _ = writeln!(out, "skybot_voice_queue_length{{guild=\"{}\"}} {}", guild_id, length);
+        }
+
+        out
+    }
+}
+
+/// Escapes a label value as the text format requires
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+async fn voice_stats(cache: &Cache, songbird: &Songbird) -> VoiceStats {
+    let mut stats = VoiceStats { connections: 0, queues: Vec::new() };
+
+    for guild_id in cache.guilds() {
+        let call = match songbird.get(guild_id) {
+            Some(call) => call,
+            None => continue,
+        };
+
+        let call = call.lock().await;
+
+        if call.current_channel().is_some() {
+            stats.connections += 1;
+            stats.queues.push((guild_id.0, call.queue().len()));
+        }
+    }
+
+    stats
+}
+
+
+/// Answers `GET /metrics` on `listener` until the process exits
+pub async fn serve(listener: TcpListener, metrics: Metrics, cache: Arc<Cache>, songbird: Arc<Songbird>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(why) => {
+                println!("Could not accept a metrics connection: {:?}", why);
+                continue;
+            }
+        };
+
+        let metrics = metrics.clone();
+        let cache = cache.clone();
+        let songbird = songbird.clone();
+
+        tokio::spawn(async move {
+            if let Err(why) = respond(stream, &metrics, &cache, &songbird).await {
+                println!("Could not answer a metrics request: {:?}", why);
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, metrics: &Metrics, cache: &Cache, songbird: &Songbird) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+
+    // Only the request line matters, but read the whole head so the client isn't cut off mid-send
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+        let read = stream.read(&mut buf).await?;
+
+        if read == 0 {
+            break;
+        }
+
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let voice = voice_stats(cache, songbird).await;
+            ("200 OK", "text/plain; version=0.0.4", metrics.render(&voice))
+        },
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Method Not Allowed\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
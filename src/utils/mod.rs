@@ -1 +1,13 @@
-pub mod utilities;
\ No newline at end of file
+pub mod audio;
+pub mod audit_log;
+pub mod embeds;
+pub mod errors;
+pub mod metrics;
+pub mod modlog;
+pub mod music_sessions;
+pub mod response;
+pub mod shutdown;
+pub mod track_display;
+pub mod track_requests;
+pub mod utilities;
+pub mod ytdlp;
\ No newline at end of file
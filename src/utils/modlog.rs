@@ -0,0 +1,76 @@
+use serenity::builder::CreateEmbed;
+use serenity::framework::standard::CommandResult;
+use serenity::model::prelude::{GuildId, Timestamp, User};
+use serenity::prelude::*;
+use serenity::utils::Colour;
+
+use crate::database::cases::Case;
+use crate::database::connection::DatabaseContainer;
+
+
+/// Longest reason a case can hold, since it is shown in a single embed field
+pub const MAX_REASON_LENGTH: usize = 1024;
+
+
+pub fn case_embed<'a>(e: &'a mut CreateEmbed, case: &Case) -> &'a mut CreateEmbed {
+    let colour = match case.action.as_str() {
+        "Ban" | "Kick" => Colour::RED,
+        "Timeout" | "Warn" => Colour::ORANGE,
+        _ => Colour::GOLD,
+    };
+
+    e.title(format!("Case #{} | {}", case.case_id, case.action))
+        .color(colour)
+        .field("• Target", &case.target, true)
+        .field("• Moderator", format!("<@{}>", case.moderator_id.0), true)
+        .field("• Reason", &case.reason, false);
+
+    if let Ok(timestamp) = Timestamp::from_unix_timestamp(case.created_at) {
+        e.timestamp(timestamp);
+    }
+
+    e
+}
+
+/// Stores a moderation action as a new case and posts it to the guild's mod-log channel, if one is set
+pub async fn record_case(ctx: &Context, guild_id: GuildId, moderator: &User, action: &str, target: String, reason: &str) -> CommandResult<Case> {
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+    let mut case = database.create_case(guild_id, action, &target, moderator.id, reason)?;
+
+    if let Some(channel_id) = database.guild_settings(guild_id)?.modlog_channel {
+        match channel_id.send_message(ctx, |m| m.embed(|e| case_embed(e, &case))).await {
+            Ok(message) => {
+                database.set_case_message(guild_id, case.case_id, channel_id, message.id)?;
+                case.message_id = Some(message.id);
+                case.channel_id = Some(channel_id);
+            },
+            // A missing or locked mod-log channel shouldn't undo the action that was already taken
+            Err(why) => println!("Could not post case {} to the mod-log of guild {}: {:?}", case.case_id, guild_id, why),
+        }
+    }
+
+    Ok(case)
+}
+
+/// Re-renders a case's mod-log message after it was changed, in the channel it was posted in
+pub async fn refresh_case(ctx: &Context, guild_id: GuildId, case: &Case) -> CommandResult {
+    let message_id = match case.message_id {
+        Some(message_id) => message_id,
+        None => return Ok(()),
+    };
+
+    // Cases posted before the channel was stored are looked for in the current mod-log
+    let channel_id = match case.channel_id {
+        Some(channel_id) => Some(channel_id),
+        None => {
+            let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+            database.guild_settings(guild_id)?.modlog_channel
+        },
+    };
+
+    if let Some(channel_id) = channel_id {
+        channel_id.edit_message(ctx, message_id, |m| m.embed(|e| case_embed(e, case))).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use serenity::builder::CreateEmbed;
+use serenity::http::Http;
+use serenity::model::prelude::{ChannelId, GuildId, MessageId};
+use serenity::prelude::TypeMapKey;
+
+use crate::utils::audio::AudioFilter;
+
+
+/// Per-guild music state that songbird doesn't keep for us, for every guild the bot has joined voice in
+pub struct MusicSessions;
+
+impl TypeMapKey for MusicSessions {
+    type Value = Arc<Mutex<HashMap<GuildId, MusicSession>>>;
+}
+
+/// What happens when a track finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    /// Move on to the next track
+    #[default]
+    Off,
+    /// Play the same track again
+    Track,
+    /// Move on, putting the finished track back at the end of the queue
+    Queue,
+}
+
+impl LoopMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LoopMode::Off => "off",
+            LoopMode::Track => "track",
+            LoopMode::Queue => "queue",
+        }
+    }
+}
+
+impl FromStr for LoopMode {
+    type Err = ();
+
+    /// Also takes `current` and `disable`, which `!loop` used to
+    fn from_str(s: &str) -> Result<LoopMode, ()> {
+        match s.to_lowercase().as_str() {
+            "off" | "disable" => Ok(LoopMode::Off),
+            "track" | "current" => Ok(LoopMode::Track),
+            "queue" => Ok(LoopMode::Queue),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for LoopMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct MusicSession {
+    /// Where the session was started from, and where announcements about it go
+    pub text_channel: ChannelId,
+    /// The live "Now playing" message with the player buttons, in `text_channel`
+    pub player_message: Option<MessageId>,
+    pub loop_mode: LoopMode,
+    /// Applied to every track, in order
+    pub filters: Vec<AudioFilter>,
+}
+
+impl MusicSession {
+    pub fn new(text_channel: ChannelId) -> MusicSession {
+        MusicSession { text_channel, player_message: None, loop_mode: LoopMode::Off, filters: Vec::new() }
+    }
+
+    /// Turns the player message into `embed` and takes its buttons away, or just sends `embed` if there isn't one
+    pub async fn close_player(&self, http: &Http, embed: CreateEmbed) {
+        if let Some(message_id) = self.player_message {
+            let edited = self.text_channel.edit_message(http, message_id, |m|
+                m.set_embed(embed.clone()).components(|c| c)
+            ).await;
+
+            if edited.is_ok() {
+                return;
+            }
+        }
+
+        let _ = self.text_channel.send_message(http, |m| m.set_embed(embed)).await;
+    }
+}
@@ -0,0 +1,116 @@
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::channel::Message;
+use serenity::model::prelude::ChannelId;
+use serenity::prelude::*;
+
+
+/// A command's reply, built once by the shared command logic and then sent
+/// either as a message (prefix commands) or as an interaction response (slash commands)
+#[derive(Debug, Clone, Default)]
+pub struct Response {
+    pub content: Option<String>,
+    pub embed: Option<CreateEmbed>,
+    /// Buttons under the reply, e.g. to change page
+    pub components: Option<CreateComponents>,
+}
+
+impl Response {
+    pub fn text(content: impl ToString) -> Response {
+        Response { content: Some(content.to_string()), ..Default::default() }
+    }
+
+    pub fn embed(f: impl FnOnce(&mut CreateEmbed) -> &mut CreateEmbed) -> Response {
+        let mut embed = CreateEmbed::default();
+        f(&mut embed);
+
+        Response { embed: Some(embed), ..Default::default() }
+    }
+
+    pub fn components(mut self, f: impl FnOnce(&mut CreateComponents) -> &mut CreateComponents) -> Response {
+        let mut components = CreateComponents::default();
+        f(&mut components);
+
+        self.components = Some(components);
+        self
+    }
+
+    pub async fn send(self, ctx: &Context, channel_id: ChannelId) -> serenity::Result<Message> {
+        channel_id.send_message(ctx, |m| {
+            if let Some(content) = self.content {
+                m.content(content);
+            }
+
+            if let Some(embed) = self.embed {
+                m.set_embed(embed);
+            }
+
+            if let Some(components) = self.components {
+                m.set_components(components);
+            }
+
+            m
+        }).await
+    }
+
+    /// Fills in the response to an interaction that was deferred with `defer`
+    pub async fn edit_original(self, ctx: &Context, interaction: &ApplicationCommandInteraction) -> serenity::Result<Message> {
+        interaction.edit_original_interaction_response(ctx, |r| {
+            if let Some(content) = self.content {
+                r.content(content);
+            }
+
+            if let Some(embed) = self.embed {
+                r.set_embed(embed);
+            }
+
+            if let Some(components) = self.components {
+                r.components(|c| {
+                    *c = components;
+                    c
+                });
+            }
+
+            r
+        }).await
+    }
+
+    /// Answers a button press with a message only the presser can see
+    pub async fn reply_ephemeral(self, ctx: &Context, interaction: &MessageComponentInteraction) -> serenity::Result<()> {
+        interaction.create_interaction_response(ctx, |r|
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.ephemeral(true);
+
+                    if let Some(content) = self.content {
+                        d.content(content);
+                    }
+
+                    if let Some(embed) = self.embed {
+                        d.set_embed(embed);
+                    }
+
+                    if let Some(components) = self.components {
+                        d.set_components(components);
+                    }
+
+                    d
+                })
+        ).await
+    }
+
+    /// Answers a button press by replacing the message the button is on
+    pub async fn update_message(self, ctx: &Context, interaction: &MessageComponentInteraction) -> serenity::Result<()> {
+        interaction.create_interaction_response(ctx, |r|
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    // Set everything, so nothing from the old message is left behind
+                    d.content(self.content.unwrap_or_default())
+                        .set_embeds(self.embed)
+                        .set_components(self.components.unwrap_or_default())
+                })
+        ).await
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use serenity::builder::CreateEmbed;
+use serenity::cache::Cache;
+use serenity::client::bridge::gateway::ShardManager;
+use serenity::http::Http;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::*;
+use serenity::utils::Colour;
+use songbird::Songbird;
+
+use crate::database::connection::DatabaseContainer;
+use crate::database::saved_queues::SavedQueue;
+use crate::utils::audit_log::AuditLogContainer;
+use crate::utils::music_sessions::MusicSessions;
+
+
+/// Shares the shard manager through the client's data map, so commands can stop the bot
+pub struct ShardManagerContainer;
+
+impl TypeMapKey for ShardManagerContainer {
+    type Value = Arc<Mutex<ShardManager>>;
+}
+
+
+/// Stops the bot in order: saves and announces every active queue, leaves every voice channel,
+/// flushes the audit log, then closes every shard so `Client::start` returns
+pub async fn shutdown(data: &RwLock<TypeMap>, cache: &Cache, http: &Http, songbird: &Songbird) {
+    let mut left = 0;
+
+    for guild_id in cache.guilds() {
+        if leave_voice(data, http, songbird, guild_id).await {
+            left += 1;
+        }
+    }
+
+    println!("Shutting down, left {} voice channel(s)", left);
+
+    let (audit_log, shard_manager) = {
+        let data = data.read().await;
+        (data.get::<AuditLogContainer>().unwrap().clone(), data.get::<ShardManagerContainer>().unwrap().clone())
+    };
+
+    if let Err(why) = audit_log.flush() {
+        println!("Could not flush the audit log: {:?}", why);
+    }
+
+    shard_manager.lock().await.shutdown_all().await;
+}
+
+/// Saves the guild's queue if anything is playing, tells its session channel, and leaves.
+/// Returns whether there was a call to leave.
+async fn leave_voice(data: &RwLock<TypeMap>, http: &Http, songbird: &Songbird, guild_id: GuildId) -> bool {
+    let call = match songbird.get(guild_id) {
+        Some(call) => call,
+        None => return false,
+    };
+
+    let mut handler = call.lock().await;
+
+    let voice_channel = match handler.current_channel() {
+        Some(channel) => channel,
+        None => return false,
+    };
+
+    let (database, session) = {
+        let data = data.read().await;
+        let session = data.get::<MusicSessions>().unwrap().lock().unwrap().remove(&guild_id);
+        (data.get::<DatabaseContainer>().unwrap().clone(), session)
+    };
+
+    let queue = handler.queue().current_queue();
+
+    if let (Some(session), Some(current)) = (session, queue.first()) {
+        let position = current.get_info().await.map(|state| state.position).unwrap_or_default();
+
+        let saved = SavedQueue {
+            guild_id,
+            voice_channel: voice_channel.0.into(),
+            text_channel: session.text_channel,
+            position,
+            tracks: queue.iter().filter_map(|track| track.metadata().source_url.clone()).collect(),
+        };
+
+        if let Err(why) = database.save_queue(&saved) {
+            println!("Could not save the queue for guild {}: {:?}", guild_id, why);
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title("Restarting")
+            .description(format!("The bot is restarting. The queue of {} track(s) was saved and will carry on when it's back.", saved.tracks.len()))
+            .color(Colour::GOLD);
+
+        session.close_player(http, embed).await;
+    }
+
+    // Stopping the queue ends every track, which would otherwise set off the end-of-track announcements
+    handler.remove_all_global_events();
+    handler.queue().stop();
+    drop(handler);
+
+    if let Err(why) = songbird.remove(guild_id).await {
+        println!("Could not leave voice in guild {}: {:?}", guild_id, why);
+    }
+
+    true
+}
@@ -0,0 +1,174 @@
+//! How tracks are shown everywhere: the player, queue listings and one-line replies.
+//! Any field of a track's metadata can be missing (livestreams have no duration, uploaded files
+//! no channel or thumbnail), so nothing here unwraps them.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serenity::builder::CreateEmbed;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::*;
+use serenity::utils::Colour;
+use songbird::input::Metadata;
+
+use crate::database::connection::DatabaseContainer;
+
+
+/// How much the player shows about the current track, set per guild
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrackStyle {
+    /// A couple of lines, no thumbnail
+    Compact,
+    /// Fields for everything we know, with the thumbnail
+    #[default]
+    Verbose,
+}
+
+impl TrackStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TrackStyle::Compact => "compact",
+            TrackStyle::Verbose => "verbose",
+        }
+    }
+}
+
+impl FromStr for TrackStyle {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<TrackStyle, ()> {
+        match s.to_lowercase().as_str() {
+            "compact" => Ok(TrackStyle::Compact),
+            "verbose" => Ok(TrackStyle::Verbose),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for TrackStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The style `guild_id` has picked, or the default if it hasn't or its settings can't be read
+pub async fn style(ctx: &Context, guild_id: GuildId) -> TrackStyle {
+    let database = ctx.data.read().await.get::<DatabaseContainer>().unwrap().clone();
+
+    database.guild_settings_or_default(guild_id).track_style.unwrap_or_default()
+}
+
+
+const UNKNOWN: &str = "Unknown";
+
+/// Characters in the progress bar, not counting the marker
+const PROGRESS_BAR_WIDTH: usize = 18;
+
+pub fn title(metadata: &Metadata) -> &str {
+    metadata.title.as_deref().unwrap_or("Unknown title")
+}
+
+pub fn author(metadata: &Metadata) -> &str {
+    metadata.channel.as_deref().or(metadata.artist.as_deref()).unwrap_or(UNKNOWN)
+}
+
+/// `3:07`, or `1:02:03` once there are hours
+pub fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// The track's length, or `Live` when it has none, as with livestreams
+pub fn length(metadata: &Metadata) -> String {
+    metadata.duration.map_or("Live".to_string(), clock)
+}
+
+/// The title as a link to the track, or plain when there's no URL
+pub fn linked_title(metadata: &Metadata) -> String {
+    // Square brackets in the title would end the link text early
+    let title = title(metadata).replace('[', "(").replace(']', ")");
+
+    match &metadata.source_url {
+        Some(url) => format!("[{}]({})", title, url),
+        None => title,
+    }
+}
+
+/// One line for replies, e.g. "**Song** by **Artist** `3:07`"
+pub fn compact(metadata: &Metadata) -> String {
+    format!("**{}** by **{}** `{}`", title(metadata), author(metadata), length(metadata))
+}
+
+/// A numbered line of a queue listing
+pub fn queue_line(position: usize, metadata: &Metadata) -> String {
+    format!("**{})** {} `{}`", position, linked_title(metadata), length(metadata))
+}
+
+/// e.g. `▬▬▬▬▬🔘▬▬▬▬▬▬▬▬▬▬▬▬▬ 1:02 / 3:07`. Without a length there's nothing to fill, so only the elapsed time is shown.
+pub fn progress_bar(elapsed: Duration, total: Option<Duration>) -> String {
+    let total = match total {
+        Some(total) if !total.is_zero() => total,
+        _ => return format!("🔴 {}", clock(elapsed)),
+    };
+
+    let fraction = (elapsed.as_secs_f64() / total.as_secs_f64()).clamp(0.0, 1.0);
+    let marker = (fraction * PROGRESS_BAR_WIDTH as f64).round() as usize;
+
+    format!(
+        "{}🔘{} {} / {}",
+        "▬".repeat(marker),
+        "▬".repeat(PROGRESS_BAR_WIDTH - marker),
+        clock(elapsed.min(total)),
+        clock(total),
+    )
+}
+
+/// Describes a track in `style`, with a progress bar if `elapsed` is given.
+/// Callers add their own title and any fields about the queue.
+pub fn track_embed<'a>(e: &'a mut CreateEmbed, metadata: &Metadata, elapsed: Option<Duration>, style: TrackStyle) -> &'a mut CreateEmbed {
+    e.color(Colour::DARK_GREEN);
+
+    if let Some(url) = &metadata.source_url {
+        e.url(url);
+    }
+
+    let progress = elapsed.map(|elapsed| progress_bar(elapsed, metadata.duration));
+
+    match style {
+        TrackStyle::Compact => {
+            let mut description = format!("{} by **{}** `{}`", linked_title(metadata), author(metadata), length(metadata));
+
+            if let Some(progress) = progress {
+                description.push('\n');
+                description.push_str(&progress);
+            }
+
+            e.description(description)
+        },
+        TrackStyle::Verbose => {
+            e.description(format!("```\n{}\n```", title(metadata)))
+                .field("• Duration", length(metadata), true)
+                .field("• Author", author(metadata), true);
+
+            if let Some(url) = &metadata.source_url {
+                e.field("• URL", format!("[Click]({})", url), true);
+            }
+
+            if let Some(progress) = progress {
+                e.field("• Progress", progress, false);
+            }
+
+            if let Some(thumbnail) = &metadata.thumbnail {
+                e.thumbnail(thumbnail);
+            }
+
+            e
+        },
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serenity::model::prelude::UserId;
+use serenity::prelude::TypeMapKey;
+use songbird::tracks::TrackHandle;
+
+
+/// Who asked for a track, when and how, kept in the track's own typemap so it follows the track around the queue
+#[derive(Debug, Clone)]
+pub struct TrackRequest {
+    pub requester: UserId,
+    /// Unix timestamp, in seconds
+    pub requested_at: i64,
+    pub source: RequestSource,
+}
+
+impl TypeMapKey for TrackRequest {
+    type Value = TrackRequest;
+}
+
+impl TrackRequest {
+    /// e.g. "<@1234> <t:1700000000:R> with `/play`"
+    pub fn describe(&self) -> String {
+        format!("<@{}> <t:{}:R> with `{}`", self.requester.0, self.requested_at, self.source)
+    }
+}
+
+/// The command a track was queued with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestSource {
+    /// `!play`, whatever the guild's prefix is
+    PrefixCommand(&'static str),
+    /// `/play`
+    SlashCommand(&'static str),
+}
+
+impl fmt::Display for RequestSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestSource::PrefixCommand(name) => write!(f, "!{}", name),
+            RequestSource::SlashCommand(name) => write!(f, "/{}", name),
+        }
+    }
+}
+
+/// Listeners who have voted to skip a track. Kept on the track, so votes never carry over to the next one.
+pub struct SkipVotes;
+
+impl TypeMapKey for SkipVotes {
+    type Value = HashSet<UserId>;
+}
+
+/// Marks a track that a command took out of the queue, so its end isn't taken for the queue moving on
+pub struct Discarded;
+
+impl TypeMapKey for Discarded {
+    type Value = ();
+}
+
+/// Marks a track that already has a copy at the back of the queue, for when the whole queue loops
+pub struct Requeued;
+
+impl TypeMapKey for Requeued {
+    type Value = ();
+}
+
+/// `None` for tracks nobody asked for directly, such as those brought back after a restart
+pub async fn request(track: &TrackHandle) -> Option<TrackRequest> {
+    track.typemap().read().await.get::<TrackRequest>().cloned()
+}
+
+pub async fn set_request(track: &TrackHandle, request: TrackRequest) {
+    track.typemap().write().await.insert::<TrackRequest>(request);
+}
+
+/// Stops a track taken out of the queue. Queued tracks wait paused in the driver, so they'd stay there otherwise.
+pub async fn discard(track: &TrackHandle) {
+    track.typemap().write().await.insert::<Discarded>(());
+    let _ = track.stop();
+}
+
+pub async fn is_discarded(track: &TrackHandle) -> bool {
+    track.typemap().read().await.contains_key::<Discarded>()
+}
+
+/// Marks `track` as requeued, returning `false` if it already was
+pub async fn mark_requeued(track: &TrackHandle) -> bool {
+    let mut typemap = track.typemap().write().await;
+
+    if typemap.contains_key::<Requeued>() {
+        return false;
+    }
+
+    typemap.insert::<Requeued>(());
+    true
+}
+
+/// Adds `voter`'s vote to skip `track`, then counts the votes of those still in `listeners`
+pub async fn vote_to_skip(track: &TrackHandle, voter: UserId, listeners: &[UserId]) -> usize {
+    let mut typemap = track.typemap().write().await;
+    let votes = typemap.entry::<SkipVotes>().or_insert_with(HashSet::new);
+
+    votes.insert(voter);
+    votes.iter().filter(|user_id| listeners.contains(user_id)).count()
+}
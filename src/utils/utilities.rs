@@ -41,6 +41,70 @@ pub fn duration_formatter(duration: Duration) -> String {
     formatted.join(", ")
 }
 
+/// Parses durations like `90`, `45s`, `10m`, `1h30m` or `2d`, or clock times like `1:23` and `1:02:03`.
+/// A bare number is read as seconds.
+pub fn duration_parser(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+
+    if input.is_empty() {
+        return None;
+    }
+
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    if input.contains(':') {
+        return clock_parser(&input);
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(Duration::from_secs(total))
+}
+
+/// `m:ss` or `h:mm:ss`, as `track_display::clock` writes them
+fn clock_parser(input: &str) -> Option<Duration> {
+    let parts = input.split(':').map(|part| part.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+
+    let (first, rest) = match parts.split_first() {
+        Some(split) if parts.len() <= 3 => split,
+        _ => return None,
+    };
+
+    // Only the leading part can go past 59
+    if rest.iter().any(|&part| part >= 60) {
+        return None;
+    }
+
+    let seconds = rest.iter().try_fold(*first, |total, &part| total.checked_mul(60)?.checked_add(part))?;
+
+    Some(Duration::from_secs(seconds))
+}
+
 pub fn num_prefix(num: usize) -> String {
     let prefixed: String;
 
@@ -61,4 +125,37 @@ pub fn num_prefix(num: usize) -> String {
     }
 
     prefixed
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(duration_parser("90"), Some(Duration::from_secs(90)));
+        assert_eq!(duration_parser("83s"), Some(Duration::from_secs(83)));
+        assert_eq!(duration_parser("1m23s"), Some(Duration::from_secs(83)));
+        assert_eq!(duration_parser("1H30M"), Some(Duration::from_secs(5400)));
+        assert_eq!(duration_parser("2d"), Some(Duration::from_secs(172800)));
+        assert_eq!(duration_parser("1:23"), Some(Duration::from_secs(83)));
+        assert_eq!(duration_parser("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(duration_parser("90:00"), Some(Duration::from_secs(5400)));
+        assert_eq!(duration_parser(" 0 "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for input in ["", "  ", "1:99", "1:60:00", "1:2:3:4", ":30", "1:", "-5", "-1:00", "5x", "1m2", "m", "1.5m"] {
+            assert_eq!(duration_parser(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert_eq!(duration_parser("99999999999999999999"), None);
+        assert_eq!(duration_parser("999999999999999999d"), None);
+        assert_eq!(duration_parser("18446744073709551615s1s"), None);
+        assert_eq!(duration_parser("999999999999999999:00:00"), None);
+    }
+}
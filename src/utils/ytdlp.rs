@@ -0,0 +1,130 @@
+use std::io;
+use std::process::Stdio;
+
+use serde::Deserialize;
+use tokio::process::Command;
+
+
+/// Picks the audio and settles on a single video, as songbird's own yt-dlp sources do
+pub const STREAM_ARGS: [&str; 7] = ["-f", "webm[abr>0]/bestaudio/best", "-R", "infinite", "--no-playlist", "--ignore-config", "--no-warnings"];
+
+
+/// What `yt-dlp --flat-playlist` knows about a playlist (or search) without resolving each entry
+#[derive(Debug, Deserialize)]
+pub struct FlatPlaylist {
+    pub title: Option<String>,
+    pub webpage_url: Option<String>,
+    /// How many entries the whole playlist has, which can be more than were listed
+    pub playlist_count: Option<usize>,
+    #[serde(default)]
+    pub entries: Vec<FlatEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FlatEntry {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub channel: Option<String>,
+    pub uploader: Option<String>,
+    /// In seconds
+    pub duration: Option<f64>,
+}
+
+impl FlatEntry {
+    /// A URL the entry can be played from. Search results sometimes only come with an ID.
+    pub fn playable_url(&self) -> Option<String> {
+        match (&self.url, &self.id) {
+            (Some(url), _) if url.starts_with("http") => Some(url.clone()),
+            (_, Some(id)) => Some(format!("https://www.youtube.com/watch?v={}", id)),
+            _ => None,
+        }
+    }
+}
+
+
+/// Whether `url` looks like it links to a playlist (or album, or SoundCloud set) rather than one track.
+/// YouTube links to a video in a playlist count, and play the playlist.
+pub fn is_playlist(url: &str) -> bool {
+    ["list=", "/playlist", "/sets/", "/album/"].iter().any(|marker| url.contains(marker))
+}
+
+/// Lists the entries of `target`, a playlist URL or a search such as `ytsearch5:query`, up to `limit` of them.
+/// The process is killed if the returned future is dropped, so this can be put under a timeout.
+pub async fn flat_playlist(target: &str, limit: Option<usize>) -> io::Result<FlatPlaylist> {
+    let mut command = Command::new("yt-dlp");
+    command.args(["--flat-playlist", "--dump-single-json", "--no-warnings", "--ignore-errors"]);
+
+    if let Some(limit) = limit {
+        command.args(["--playlist-end", &limit.to_string()]);
+    }
+
+    let output = command
+        .arg(target)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await?;
+
+    if !output.status.success() && output.stdout.is_empty() {
+        return Err(io::Error::other(format!("yt-dlp exited with {}", output.status)));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(io::Error::from)
+}
+
+/// Everything yt-dlp knows about the single video `target`, as `Metadata::from_ytdl_output` takes it
+pub async fn info(target: &str) -> io::Result<serde_json::Value> {
+    let output = Command::new("yt-dlp")
+        .arg("-j")
+        .args(STREAM_ARGS)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!("yt-dlp exited with {}", output.status)));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(io::Error::from)
+}
+
+/// The first `limit` YouTube results for `query`
+pub async fn search(query: &str, limit: usize) -> io::Result<Vec<FlatEntry>> {
+    Ok(flat_playlist(&format!("ytsearch{}:{}", limit, query), None).await?.entries)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_playlists() {
+        for url in [
+            "https://www.youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG",
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG",
+            "https://soundcloud.com/artist/sets/some-set",
+            "https://artist.bandcamp.com/album/some-album",
+            "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M",
+        ] {
+            assert!(is_playlist(url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn single_tracks_are_not_playlists() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://soundcloud.com/artist/some-track",
+            "https://artist.bandcamp.com/track/some-track",
+        ] {
+            assert!(!is_playlist(url), "{}", url);
+        }
+    }
+}